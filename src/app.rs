@@ -1,19 +1,73 @@
-use clap::{App, Arg, crate_name, crate_authors, crate_version};
+use clap::{App, AppSettings, Arg, crate_name, crate_authors, crate_version};
 
 pub fn app() -> App<'static, 'static> {
     App::new(crate_name!())
         .version(crate_version!())
         .author(crate_authors!())
+        .arg(AppDataDir::create())
+        .arg(AppProfile::create())
         .subcommand(New::create())
         .subcommand(Remove::create())
+        .subcommand(Rename::create())
+        .subcommand(Move::create())
+        .subcommand(Copy::create())
         .subcommand(Tasks::create())
         .subcommand(Groups::create())
+        .subcommand(Group::create())
         .subcommand(Use::create())
         .subcommand(Start::create())
+        .subcommand(Pomodoro::create())
         .subcommand(Stop::create())
+        .subcommand(Log::create())
+        .subcommand(Adjust::create())
+        .subcommand(Sessions::create())
+        .subcommand(Session::create())
+        .subcommand(Edit::create())
+        .subcommand(Pause::create())
+        .subcommand(Resume::create())
+        .subcommand(Check::create())
         .subcommand(Tomorrow::create())
         .subcommand(Complete::create())
         .subcommand(Archive::create())
+        .subcommand(Archived::create())
+        .subcommand(Unarchive::create())
+        .subcommand(Undo::create())
+        .subcommand(Redo::create())
+        .subcommand(Events::create())
+        .subcommand(Profile::create())
+        .subcommand(Profiles::create())
+        .subcommand(Config::create())
+        .subcommand(MigrateStorage::create())
+}
+
+// --- GLOBAL ARGS ---
+
+pub struct AppDataDir;
+impl AppDataDir {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("data-dir")
+            .takes_value(true)
+            .global(true)
+    }
+
+    pub fn name() -> &'static str {
+        "data-dir"
+    }
+}
+
+pub struct AppProfile;
+impl AppProfile {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("profile")
+            .takes_value(true)
+            .global(true)
+    }
+
+    pub fn name() -> &'static str {
+        "profile"
+    }
 }
 
 // --- NEW SUBCOMMAND ---
@@ -70,12 +124,138 @@ impl RemoveValue {
     }
 }
 
+// --- RENAME SUBCOMMAND ---
+
+pub struct Rename;
+impl Rename {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(RenameValue::create())
+            .arg(RenameName::create())
+    }
+
+    pub fn name() -> &'static str {
+        "rename"
+    }
+}
+
+pub struct RenameValue;
+impl RenameValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "rename-value"
+    }
+}
+
+pub struct RenameName;
+impl RenameName {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(2)
+    }
+
+    pub fn name() -> &'static str {
+        "rename-name"
+    }
+}
+
+// --- MOVE SUBCOMMAND ---
+
+pub struct Move;
+impl Move {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(MoveValue::create())
+            .arg(MoveTo::create())
+    }
+
+    pub fn name() -> &'static str {
+        "move"
+    }
+}
+
+pub struct MoveValue;
+impl MoveValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "move-value"
+    }
+}
+
+pub struct MoveTo;
+impl MoveTo {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("to")
+            .takes_value(true)
+            .required(true)
+    }
+
+    pub fn name() -> &'static str {
+        "move-to"
+    }
+}
+
+// --- COPY SUBCOMMAND ---
+
+pub struct Copy;
+impl Copy {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(CopyValue::create())
+            .arg(CopyTo::create())
+    }
+
+    pub fn name() -> &'static str {
+        "copy"
+    }
+}
+
+pub struct CopyValue;
+impl CopyValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "copy-value"
+    }
+}
+
+pub struct CopyTo;
+impl CopyTo {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("to")
+            .takes_value(true)
+            .required(true)
+    }
+
+    pub fn name() -> &'static str {
+        "copy-to"
+    }
+}
+
 // --- TASKS SUBCOMMAND ---
 
 pub struct Tasks;
 impl Tasks {
     fn create() -> App<'static, 'static> {
         App::new(Self::name())
+            .arg(TasksAsOf::create())
     }
 
     pub fn name() -> &'static str {
@@ -83,12 +263,26 @@ impl Tasks {
     }
 }
 
+pub struct TasksAsOf;
+impl TasksAsOf {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("as-of")
+            .takes_value(true)
+    }
+
+    pub fn name() -> &'static str {
+        "tasks-as-of"
+    }
+}
+
 // --- GROUPS SUBCOMMAND ---
 
 pub struct Groups;
 impl Groups {
     fn create() -> App<'static, 'static> {
         App::new(Self::name())
+            .arg(GroupsAsOf::create())
     }
 
     pub fn name() -> &'static str {
@@ -96,6 +290,72 @@ impl Groups {
     }
 }
 
+pub struct GroupsAsOf;
+impl GroupsAsOf {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("as-of")
+            .takes_value(true)
+    }
+
+    pub fn name() -> &'static str {
+        "groups-as-of"
+    }
+}
+
+// --- GROUP SUBCOMMAND ---
+
+pub struct Group;
+impl Group {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .subcommand(GroupRename::create())
+    }
+
+    pub fn name() -> &'static str {
+        "group"
+    }
+}
+
+pub struct GroupRename;
+impl GroupRename {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(GroupRenameValue::create())
+            .arg(GroupRenameName::create())
+    }
+
+    pub fn name() -> &'static str {
+        "rename"
+    }
+}
+
+pub struct GroupRenameValue;
+impl GroupRenameValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "group-rename-value"
+    }
+}
+
+pub struct GroupRenameName;
+impl GroupRenameName {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(2)
+    }
+
+    pub fn name() -> &'static str {
+        "group-rename-name"
+    }
+}
+
 // --- USE SUBCOMMAND ---
 
 pub struct Use;
@@ -142,6 +402,10 @@ impl Start {
     fn create() -> App<'static, 'static> {
         App::new(Self::name())
             .arg(StartValue::create())
+            .arg(StartNote::create())
+            .arg(StartAt::create())
+            .arg(StartParallel::create())
+            .arg(StartFor::create())
     }
 
     pub fn name() -> &'static str {
@@ -162,88 +426,161 @@ impl StartValue {
     }
 }
 
-// --- STOP SUBCOMMAND ---
+pub struct StartNote;
+impl StartNote {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .short("n")
+            .long("note")
+            .takes_value(true)
+    }
 
-pub struct Stop;
-impl Stop {
-    fn create() -> App<'static, 'static> {
-        App::new(Self::name())
+    pub fn name() -> &'static str {
+        "start-note"
+    }
+}
+
+pub struct StartAt;
+impl StartAt {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("at")
+            .takes_value(true)
+            .allow_hyphen_values(true)
     }
 
     pub fn name() -> &'static str {
-        "stop"
+        "start-at"
     }
 }
 
-// --- TOMORROW SUBCOMMAND ---
+pub struct StartParallel;
+impl StartParallel {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("parallel")
+    }
 
-pub struct Tomorrow;
-impl Tomorrow {
-    fn create() -> App<'static, 'static> {
-        App::new(Self::name())
+    pub fn name() -> &'static str {
+        "start-parallel"
+    }
+}
+
+pub struct StartFor;
+impl StartFor {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("for")
+            .takes_value(true)
     }
 
     pub fn name() -> &'static str {
-        "tmrw"
+        "start-for"
     }
 }
 
-// --- COMPLETE SUBCOMMAND ---
+// --- POMODORO SUBCOMMAND ---
 
-pub struct Complete;
-impl Complete {
+pub struct Pomodoro;
+impl Pomodoro {
     fn create() -> App<'static, 'static> {
         App::new(Self::name())
-            .arg(CompleteValue::create())
-            .arg(CompleteCurrent::create())
+            .arg(PomodoroValue::create())
+            .arg(PomodoroNote::create())
+            .arg(PomodoroParallel::create())
     }
 
     pub fn name() -> &'static str {
-        "complete"
+        "pomodoro"
     }
 }
 
-pub struct CompleteValue;
-impl CompleteValue {
+pub struct PomodoroValue;
+impl PomodoroValue {
     fn create() -> Arg<'static, 'static> {
         Arg::with_name(Self::name())
+            .required(true)
             .index(1)
     }
 
     pub fn name() -> &'static str {
-        "complete-value"
+        "pomodoro-value"
     }
 }
 
-pub struct CompleteCurrent;
-impl CompleteCurrent {
+pub struct PomodoroNote;
+impl PomodoroNote {
     fn create() -> Arg<'static, 'static> {
         Arg::with_name(Self::name())
-            .short("c")
+            .short("n")
+            .long("note")
+            .takes_value(true)
     }
 
     pub fn name() -> &'static str {
-        "complete-curr"
+        "pomodoro-note"
     }
 }
 
-// --- ARCHIVE SUBCOMMAND ---
+pub struct PomodoroParallel;
+impl PomodoroParallel {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("parallel")
+    }
 
-pub struct Archive;
-impl Archive {
+    pub fn name() -> &'static str {
+        "pomodoro-parallel"
+    }
+}
+
+// --- STOP SUBCOMMAND ---
+
+pub struct Stop;
+impl Stop {
     fn create() -> App<'static, 'static> {
         App::new(Self::name())
-            .arg(ArchiveValue::create())
-            .arg(ArchiveRetain::create())
+            .arg(StopAt::create())
     }
 
     pub fn name() -> &'static str {
-        "archive"
+        "stop"
     }
 }
 
-pub struct ArchiveValue;
-impl ArchiveValue {
+pub struct StopAt;
+impl StopAt {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("at")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+    }
+
+    pub fn name() -> &'static str {
+        "stop-at"
+    }
+}
+
+// --- LOG SUBCOMMAND ---
+
+pub struct Log;
+impl Log {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(LogValue::create())
+            .arg(LogDuration::create())
+            .arg(LogAt::create())
+            .arg(LogNote::create())
+    }
+
+    pub fn name() -> &'static str {
+        "log"
+    }
+}
+
+pub struct LogValue;
+impl LogValue {
     fn create() -> Arg<'static, 'static> {
         Arg::with_name(Self::name())
             .required(true)
@@ -251,18 +588,712 @@ impl ArchiveValue {
     }
 
     pub fn name() -> &'static str {
-        "archive-value"
+        "log-value"
     }
 }
 
-pub struct ArchiveRetain;
-impl ArchiveRetain {
+pub struct LogDuration;
+impl LogDuration {
     fn create() -> Arg<'static, 'static> {
         Arg::with_name(Self::name())
-            .short("retain")
+            .required(true)
+            .index(2)
     }
 
     pub fn name() -> &'static str {
-        "archive-retain"
+        "log-duration"
+    }
+}
+
+pub struct LogAt;
+impl LogAt {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("at")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+    }
+
+    pub fn name() -> &'static str {
+        "log-at"
+    }
+}
+
+pub struct LogNote;
+impl LogNote {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .short("n")
+            .long("note")
+            .takes_value(true)
+    }
+
+    pub fn name() -> &'static str {
+        "log-note"
+    }
+}
+
+// --- ADJUST SUBCOMMAND ---
+
+pub struct Adjust;
+impl Adjust {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            // Adjustments like -15m would otherwise be read as flags
+            .setting(AppSettings::AllowLeadingHyphen)
+            .arg(AdjustValue::create())
+            .arg(AdjustDuration::create())
+            .arg(AdjustNote::create())
+    }
+
+    pub fn name() -> &'static str {
+        "adjust"
+    }
+}
+
+pub struct AdjustValue;
+impl AdjustValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "adjust-value"
+    }
+}
+
+pub struct AdjustDuration;
+impl AdjustDuration {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(2)
+            .allow_hyphen_values(true)
+    }
+
+    pub fn name() -> &'static str {
+        "adjust-duration"
+    }
+}
+
+pub struct AdjustNote;
+impl AdjustNote {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .short("n")
+            .long("note")
+            .takes_value(true)
+    }
+
+    pub fn name() -> &'static str {
+        "adjust-note"
+    }
+}
+
+// --- SESSIONS SUBCOMMAND ---
+
+pub struct Sessions;
+impl Sessions {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(SessionsValue::create())
+    }
+
+    pub fn name() -> &'static str {
+        "sessions"
+    }
+}
+
+pub struct SessionsValue;
+impl SessionsValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "sessions-value"
+    }
+}
+
+// --- SESSION SUBCOMMAND ---
+
+pub struct Session;
+impl Session {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .subcommand(SessionEdit::create())
+            .subcommand(SessionRemove::create())
+    }
+
+    pub fn name() -> &'static str {
+        "session"
+    }
+}
+
+pub struct SessionEdit;
+impl SessionEdit {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(SessionEditValue::create())
+            .arg(SessionEditNumber::create())
+            .arg(SessionEditStart::create())
+            .arg(SessionEditEnd::create())
+    }
+
+    pub fn name() -> &'static str {
+        "edit"
+    }
+}
+
+pub struct SessionEditValue;
+impl SessionEditValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "session-edit-value"
+    }
+}
+
+pub struct SessionEditNumber;
+impl SessionEditNumber {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(2)
+    }
+
+    pub fn name() -> &'static str {
+        "session-edit-number"
+    }
+}
+
+pub struct SessionEditStart;
+impl SessionEditStart {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("start")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+    }
+
+    pub fn name() -> &'static str {
+        "session-edit-start"
+    }
+}
+
+pub struct SessionEditEnd;
+impl SessionEditEnd {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("end")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+    }
+
+    pub fn name() -> &'static str {
+        "session-edit-end"
+    }
+}
+
+pub struct SessionRemove;
+impl SessionRemove {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(SessionRemoveValue::create())
+            .arg(SessionRemoveNumber::create())
+    }
+
+    pub fn name() -> &'static str {
+        "rm"
+    }
+}
+
+pub struct SessionRemoveValue;
+impl SessionRemoveValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "session-rm-value"
+    }
+}
+
+pub struct SessionRemoveNumber;
+impl SessionRemoveNumber {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(2)
+    }
+
+    pub fn name() -> &'static str {
+        "session-rm-number"
+    }
+}
+
+// --- EDIT SUBCOMMAND ---
+
+pub struct Edit;
+impl Edit {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(EditValue::create())
+    }
+
+    pub fn name() -> &'static str {
+        "edit"
+    }
+}
+
+pub struct EditValue;
+impl EditValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "edit-value"
+    }
+}
+
+// --- PAUSE SUBCOMMAND ---
+
+pub struct Pause;
+impl Pause {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(PauseAt::create())
+    }
+
+    pub fn name() -> &'static str {
+        "pause"
+    }
+}
+
+pub struct PauseAt;
+impl PauseAt {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("at")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+    }
+
+    pub fn name() -> &'static str {
+        "pause-at"
+    }
+}
+
+// --- RESUME SUBCOMMAND ---
+
+pub struct Resume;
+impl Resume {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(ResumeAt::create())
+            .arg(ResumeParallel::create())
+    }
+
+    pub fn name() -> &'static str {
+        "resume"
+    }
+}
+
+pub struct ResumeAt;
+impl ResumeAt {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("at")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+    }
+
+    pub fn name() -> &'static str {
+        "resume-at"
+    }
+}
+
+pub struct ResumeParallel;
+impl ResumeParallel {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("parallel")
+    }
+
+    pub fn name() -> &'static str {
+        "resume-parallel"
+    }
+}
+
+// --- CHECK SUBCOMMAND ---
+
+pub struct Check;
+impl Check {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+    }
+
+    pub fn name() -> &'static str {
+        "check"
+    }
+}
+
+// --- TOMORROW SUBCOMMAND ---
+
+pub struct Tomorrow;
+impl Tomorrow {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(TomorrowCarry::create())
+    }
+
+    pub fn name() -> &'static str {
+        "tmrw"
+    }
+}
+
+pub struct TomorrowCarry;
+impl TomorrowCarry {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("carry")
+            .takes_value(true)
+            .possible_values(&["copy", "move"])
+    }
+
+    pub fn name() -> &'static str {
+        "tmrw-carry"
+    }
+}
+
+// --- COMPLETE SUBCOMMAND ---
+
+pub struct Complete;
+impl Complete {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(CompleteValue::create())
+            .arg(CompleteCurrent::create())
+            .arg(CompleteAt::create())
+    }
+
+    pub fn name() -> &'static str {
+        "complete"
+    }
+}
+
+pub struct CompleteValue;
+impl CompleteValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "complete-value"
+    }
+}
+
+pub struct CompleteCurrent;
+impl CompleteCurrent {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .short("c")
+    }
+
+    pub fn name() -> &'static str {
+        "complete-curr"
+    }
+}
+
+pub struct CompleteAt;
+impl CompleteAt {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .long("at")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+    }
+
+    pub fn name() -> &'static str {
+        "complete-at"
+    }
+}
+
+// --- ARCHIVE SUBCOMMAND ---
+
+pub struct Archive;
+impl Archive {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(ArchiveValue::create())
+            .arg(ArchiveRetain::create())
+    }
+
+    pub fn name() -> &'static str {
+        "archive"
+    }
+}
+
+pub struct ArchiveValue;
+impl ArchiveValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "archive-value"
+    }
+}
+
+pub struct ArchiveRetain;
+impl ArchiveRetain {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .short("retain")
+    }
+
+    pub fn name() -> &'static str {
+        "archive-retain"
+    }
+}
+
+// --- ARCHIVED SUBCOMMAND ---
+
+pub struct Archived;
+impl Archived {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(ArchivedValue::create())
+            .arg(ArchivedShow::create())
+    }
+
+    pub fn name() -> &'static str {
+        "archived"
+    }
+}
+
+pub struct ArchivedValue;
+impl ArchivedValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "archived-value"
+    }
+}
+
+pub struct ArchivedShow;
+impl ArchivedShow {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .short("s")
+            .long("show")
+            .takes_value(true)
+    }
+
+    pub fn name() -> &'static str {
+        "archived-show"
+    }
+}
+
+// --- UNARCHIVE SUBCOMMAND ---
+
+pub struct Unarchive;
+impl Unarchive {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(UnarchiveValue::create())
+    }
+
+    pub fn name() -> &'static str {
+        "unarchive"
+    }
+}
+
+pub struct UnarchiveValue;
+impl UnarchiveValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "unarchive-value"
+    }
+}
+
+// --- UNDO SUBCOMMAND ---
+
+pub struct Undo;
+impl Undo {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+    }
+
+    pub fn name() -> &'static str {
+        "undo"
+    }
+}
+
+// --- REDO SUBCOMMAND ---
+
+pub struct Redo;
+impl Redo {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+    }
+
+    pub fn name() -> &'static str {
+        "redo"
+    }
+}
+
+// --- EVENTS SUBCOMMAND ---
+
+pub struct Events;
+impl Events {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+    }
+
+    pub fn name() -> &'static str {
+        "events"
+    }
+}
+
+// --- PROFILE SUBCOMMAND ---
+
+pub struct Profile;
+impl Profile {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(ProfileValue::create())
+            .arg(ProfileReset::create())
+    }
+
+    pub fn name() -> &'static str {
+        "profile"
+    }
+}
+
+pub struct ProfileValue;
+impl ProfileValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "profile-value"
+    }
+}
+
+pub struct ProfileReset;
+impl ProfileReset {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .short("r")
+    }
+
+    pub fn name() -> &'static str {
+        "profile-reset"
+    }
+}
+
+// --- PROFILES SUBCOMMAND ---
+
+pub struct Profiles;
+impl Profiles {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+    }
+
+    pub fn name() -> &'static str {
+        "profiles"
+    }
+}
+
+// --- CONFIG SUBCOMMAND ---
+
+pub struct Config;
+impl Config {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(ConfigKey::create())
+            .arg(ConfigValue::create())
+    }
+
+    pub fn name() -> &'static str {
+        "config"
+    }
+}
+
+pub struct ConfigKey;
+impl ConfigKey {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "config-key"
+    }
+}
+
+pub struct ConfigValue;
+impl ConfigValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .index(2)
+    }
+
+    pub fn name() -> &'static str {
+        "config-value"
+    }
+}
+
+// --- MIGRATE STORAGE SUBCOMMAND ---
+
+pub struct MigrateStorage;
+impl MigrateStorage {
+    fn create() -> App<'static, 'static> {
+        App::new(Self::name())
+            .arg(MigrateStorageValue::create())
+    }
+
+    pub fn name() -> &'static str {
+        "migrate-storage"
+    }
+}
+
+pub struct MigrateStorageValue;
+impl MigrateStorageValue {
+    fn create() -> Arg<'static, 'static> {
+        Arg::with_name(Self::name())
+            .required(true)
+            .possible_values(&["json", "sqlite"])
+            .index(1)
+    }
+
+    pub fn name() -> &'static str {
+        "migrate-storage-value"
     }
 }
@@ -0,0 +1,164 @@
+///
+/// Groups that were archived out of the manager.
+/// The archive is kept apart from the main data so the main data
+/// stays small, while the history is still available.
+/// 
+use prettytable::{Row, row};
+use serde::{Serialize, Deserialize};
+
+use crate::manager::Group;
+use crate::migrate;
+use crate::table::TableDisplay;
+use crate::time;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Archive {
+    version: u64,
+    groups: Vec<ArchivedGroup>
+}
+
+/// A group along with when it was archived.
+/// Archived groups get their own ID, since group IDs
+/// are reused by the manager once minimized
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ArchivedGroup {
+    archive_id: usize,
+    archived_at: i64,
+    #[serde(flatten)]
+    group: Group
+}
+
+impl Default for Archive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Archive {
+    pub fn new() -> Self {
+        Archive {
+            version: migrate::SCHEMA_VERSION,
+            groups: Vec::new()
+        }
+    }
+
+    /// Add a group to the archive, returning its archive ID
+    pub fn add(&mut self, group: Group) -> usize {
+        let archive_id = self.groups.iter()
+            .map(|g| g.archive_id)
+            .max()
+            .unwrap_or(0) + 1;
+
+        self.groups.push(ArchivedGroup {
+            archive_id,
+            archived_at: time::timestamp(),
+            group
+        });
+
+        archive_id
+    }
+
+    /// Remove a group from the archive
+    pub fn take(&mut self, archive_id: usize) -> Option<Group> {
+        let index = self.groups.iter().position(|g| g.archive_id == archive_id)?;
+        Some(self.groups.remove(index).group)
+    }
+
+    pub fn get(&self, archive_id: usize) -> Option<&ArchivedGroup> {
+        self.groups.iter().find(|g| g.archive_id == archive_id)
+    }
+
+    /// The archived groups whose name, or the name of any of their tasks,
+    /// contains the query. Case insensitive
+    pub fn search(&self, query: &str) -> Archive {
+        let query = query.to_lowercase();
+        let matches = |name: &String| name.to_lowercase().contains(&query);
+
+        Archive {
+            version: self.version,
+            groups: self.groups.iter()
+                .filter(|g| matches(g.group.name())
+                    || g.group.tasks().iter().any(|t| matches(t.name())))
+                .cloned()
+                .collect()
+        }
+    }
+
+    /// The archived group with the unique ID, or holding the task with it
+    pub fn find_uid(&self, uid: u64) -> Option<&ArchivedGroup> {
+        self.groups.iter()
+            .find(|g| g.group.uid() == uid || g.group.tasks().iter().any(|t| t.uid() == uid))
+    }
+
+    /// The groups that are in only one of the archives. Toggling
+    /// these in either archive turns it into the other
+    pub fn difference(&self, other: &Archive) -> Archive {
+        let only_in = |a: &Archive, b: &Archive| a.groups.iter()
+            .filter(|g| b.get(g.archive_id).is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut groups = only_in(self, other);
+        groups.extend(only_in(other, self));
+
+        Archive {
+            version: self.version,
+            groups
+        }
+    }
+
+    /// Take out the groups of the changes that are archived,
+    /// and put back the ones that are not
+    pub fn toggle(&mut self, changes: Archive) {
+        for group in changes.groups {
+            match self.groups.iter().position(|g| g.archive_id == group.archive_id) {
+                Some(index) => { self.groups.remove(index); },
+                None => self.groups.push(group)
+            }
+        }
+
+        self.groups.sort_by_key(|g| g.archive_id);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+impl ArchivedGroup {
+
+    // GETTERS
+
+    pub fn archive_id(&self) -> usize {
+        self.archive_id
+    }
+
+    pub fn archived_at(&self) -> i64 {
+        self.archived_at
+    }
+
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+}
+
+// --- Table Display ---
+
+impl TableDisplay for Archive {
+
+    fn header(&self) -> Row {
+        row!["ID", "Group", "Tasks", "Time Tracked", "Archived"]
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        self.groups.iter()
+            .map(|g| row![
+                g.archive_id,
+                g.group.name(),
+                g.group.tasks().len(),
+                time::duration_str(g.group.tracked()),
+                time::to_local_datetime(g.archived_at).format("%B %e %Y")
+            ])
+            .collect()
+    }
+}
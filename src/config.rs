@@ -0,0 +1,269 @@
+///
+/// User settings shared by every profile.
+/// Kept as config.json in the base data directory.
+/// 
+use std::fs;
+use std::path::Path;
+
+use chrono::{NaiveTime, Timelike};
+use prettytable::{Row, row};
+use serde::{Serialize, Deserialize};
+
+use crate::{Res, ResErr};
+use crate::file::FileAccess;
+use crate::idle::IdleLimits;
+use crate::manager::CarryMode;
+use crate::table::TableDisplay;
+use crate::time;
+
+const FILE_NAME: &str = "config.json";
+
+/// Split sessions that cross the day start, moving the part after it
+/// into the next day's group
+pub const SPLIT_DAYS: &str = "split-days";
+
+/// The hour of the day (0-23) a new day starts at.
+/// Late work before it still counts towards the day before
+pub const DAY_START: &str = "day-start";
+
+/// How long a task can run before it is reported as idle, e.g. "4h"
+pub const IDLE_AFTER: &str = "idle-after";
+
+/// The time of day, e.g. "18:00", after which running tasks are reported as idle
+pub const DAY_END: &str = "day-end";
+
+/// How long a pomodoro lasts, e.g. "25m"
+pub const POMODORO: &str = "pomodoro";
+
+/// The break after most pomodoros
+pub const SHORT_BREAK: &str = "short-break";
+
+/// The break after every few pomodoros, see LONG_BREAK_EVERY
+pub const LONG_BREAK: &str = "long-break";
+
+/// How many pomodoros to do before a long break
+pub const LONG_BREAK_EVERY: &str = "long-break-every";
+
+/// Whether to copy or move unfinished tasks into each new day's group, or off
+pub const CARRY_OVER: &str = "carry-over";
+
+/// Turns off an optional setting
+const OFF: &str = "off";
+
+const DEFAULT_POMODORO: i64 = 25 * 60;
+const DEFAULT_SHORT_BREAK: i64 = 5 * 60;
+const DEFAULT_LONG_BREAK: i64 = 15 * 60;
+const DEFAULT_LONG_BREAK_EVERY: usize = 4;
+
+/// The settings that can be changed with track config
+pub const SETTINGS: &[&str] = &[
+    SPLIT_DAYS, DAY_START, IDLE_AFTER, DAY_END, POMODORO, SHORT_BREAK, LONG_BREAK, LONG_BREAK_EVERY, CARRY_OVER
+];
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Config {
+    /// The active profile. None is the default profile
+    #[serde(default)]
+    profile: Option<String>,
+
+    #[serde(default)]
+    split_days: bool,
+
+    #[serde(default)]
+    day_start: u32,
+
+    /// Seconds
+    #[serde(default)]
+    idle_after: Option<i64>,
+
+    /// Minutes into the day
+    #[serde(default)]
+    day_end: Option<u32>,
+
+    /// Pomodoro and break lengths in seconds. None uses the default
+    #[serde(default)]
+    pomodoro: Option<i64>,
+
+    #[serde(default)]
+    short_break: Option<i64>,
+
+    #[serde(default)]
+    long_break: Option<i64>,
+
+    #[serde(default)]
+    long_break_every: Option<usize>,
+
+    #[serde(default)]
+    carry_over: Option<CarryMode>
+}
+
+impl Config {
+
+    /// Load the config from the base directory.
+    /// A missing file gives the default config
+    pub fn load(base: &Path) -> Res<Config> {
+        let file_path = base.join(FILE_NAME);
+
+        if !file_path.exists() {
+            return Ok(Config::default());
+        }
+
+        let file = fs::File::open(&file_path)?;
+        let config = serde_json::from_reader(file)
+            .map_err(|err| format!("Could not read {}: {}", file_path.display(), err))?;
+
+        Ok(config)
+    }
+
+    /// Save the config to the base directory, replacing the file
+    /// in one step so a crash can't leave it half written
+    pub fn save(&self, base: &Path) -> Res<()> {
+        fs::create_dir_all(base)?;
+        FileAccess::with_file(base, FILE_NAME).without_backups().write(self)
+    }
+
+    /// Get a setting as text
+    pub fn get(&self, key: &str) -> Res<String> {
+        match key {
+            SPLIT_DAYS => Ok(self.split_days.to_string()),
+            DAY_START => Ok(format!("{:02}:00", self.day_start)),
+            IDLE_AFTER => Ok(self.idle_after.map(time::duration_arg).unwrap_or_else(|| String::from(OFF))),
+            DAY_END => Ok(self.day_end
+                .map(|m| format!("{:02}:{:02}", m / 60, m % 60))
+                .unwrap_or_else(|| String::from(OFF))),
+            POMODORO => Ok(time::duration_arg(self.pomodoro())),
+            SHORT_BREAK => Ok(time::duration_arg(self.short_break())),
+            LONG_BREAK => Ok(time::duration_arg(self.long_break())),
+            LONG_BREAK_EVERY => Ok(self.long_break_every().to_string()),
+            CARRY_OVER => Ok(String::from(self.carry_over.map(|m| m.name()).unwrap_or(OFF))),
+            _ => Err(Self::unknown(key))
+        }
+    }
+
+    /// Change a setting from text
+    pub fn set(&mut self, key: &str, value: &str) -> Res<()> {
+        match key {
+            SPLIT_DAYS => self.split_days = Self::parse_bool(key, value)?,
+            DAY_START => self.day_start = Self::parse_hour(key, value)?,
+            IDLE_AFTER if value == OFF => self.idle_after = None,
+            IDLE_AFTER => self.idle_after = Some(time::parse_duration(value)?).filter(|s| *s > 0),
+            DAY_END if value == OFF => self.day_end = None,
+            DAY_END => self.day_end = Some(Self::parse_time_of_day(key, value)?),
+            POMODORO => self.pomodoro = Some(Self::parse_length(key, value)?),
+            SHORT_BREAK => self.short_break = Some(Self::parse_length(key, value)?),
+            LONG_BREAK => self.long_break = Some(Self::parse_length(key, value)?),
+            LONG_BREAK_EVERY => self.long_break_every = match value.parse::<usize>() {
+                Ok(every) if every > 0 => Some(every),
+                _ => return Err(ResErr::from(format!("{} must be a number above 0, not {}", key, value)))
+            },
+            CARRY_OVER if value == OFF => self.carry_over = None,
+            CARRY_OVER => self.carry_over = Some(value.parse::<CarryMode>()?),
+            _ => return Err(Self::unknown(key))
+        }
+
+        Ok(())
+    }
+
+    fn parse_bool(key: &str, value: &str) -> Res<bool> {
+        match value {
+            "true" | "on" | "yes" => Ok(true),
+            "false" | "off" | "no" => Ok(false),
+            _ => Err(ResErr::from(format!("{} must be true or false, not {}", key, value)))
+        }
+    }
+
+    /// Parse an hour of the day, as "4", "04" or "04:00"
+    fn parse_hour(key: &str, value: &str) -> Res<u32> {
+        let hour = value.strip_suffix(":00").unwrap_or(value);
+
+        match hour.parse::<u32>() {
+            Ok(hour) if hour < 24 => Ok(hour),
+            _ => Err(ResErr::from(format!("{} must be an hour from 0 to 23, not {}", key, value)))
+        }
+    }
+
+    /// Parse a length of time, "25m", that is more than zero
+    fn parse_length(key: &str, value: &str) -> Res<i64> {
+        match time::parse_duration(value)? {
+            seconds if seconds > 0 => Ok(seconds),
+            _ => Err(ResErr::from(format!("{} must be longer than zero, not {}", key, value)))
+        }
+    }
+
+    /// Parse a time of day, "18:00", into minutes into the day
+    fn parse_time_of_day(key: &str, value: &str) -> Res<u32> {
+        NaiveTime::parse_from_str(value, "%H:%M")
+            .map(|t| t.hour() * 60 + t.minute())
+            .map_err(|_| ResErr::from(format!("{} must be a time like 18:00, or off, not {}", key, value)))
+    }
+
+    fn unknown(key: &str) -> ResErr {
+        ResErr::from(format!("Unknown setting: {}. Settings are: {}", key, SETTINGS.join(", ")))
+    }
+
+    // GETTERS/SETTERS
+
+    pub fn profile(&self) -> Option<&String> {
+        self.profile.as_ref()
+    }
+
+    pub fn set_profile(&mut self, profile: Option<String>) {
+        self.profile = profile;
+    }
+
+    pub fn split_days(&self) -> bool {
+        self.split_days
+    }
+
+    pub fn day_start(&self) -> u32 {
+        self.day_start
+    }
+
+    pub fn idle_limits(&self) -> IdleLimits {
+        IdleLimits::new(self.idle_after, self.day_end)
+    }
+
+    pub fn carry_over(&self) -> Option<CarryMode> {
+        self.carry_over
+    }
+
+    pub fn pomodoro(&self) -> i64 {
+        self.pomodoro.unwrap_or(DEFAULT_POMODORO)
+    }
+
+    pub fn short_break(&self) -> i64 {
+        self.short_break.unwrap_or(DEFAULT_SHORT_BREAK)
+    }
+
+    pub fn long_break(&self) -> i64 {
+        self.long_break.unwrap_or(DEFAULT_LONG_BREAK)
+    }
+
+    pub fn long_break_every(&self) -> usize {
+        self.long_break_every.unwrap_or(DEFAULT_LONG_BREAK_EVERY)
+    }
+
+    /// The break to take after the given number of pomodoros.
+    /// Every few pomodoros the break is a long one
+    pub fn break_after(&self, pomodoros: usize) -> i64 {
+        if pomodoros > 0 && pomodoros.is_multiple_of(self.long_break_every()) {
+            self.long_break()
+        } else {
+            self.short_break()
+        }
+    }
+}
+
+// --- Table Display ---
+
+impl TableDisplay for Config {
+    fn header(&self) -> Row {
+        row!["Setting", "Value"]
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        SETTINGS.iter()
+            .map(|key| row![key, self.get(key).unwrap_or_default()])
+            .collect()
+    }
+}
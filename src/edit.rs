@@ -0,0 +1,528 @@
+///
+/// Edit a group as text in the user's editor.
+/// The group is rendered as a readable form, which is parsed
+/// and validated once the editor exits.
+///
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::process::{self, Command};
+
+use crate::manager::{Group, SessionKind, Timebox};
+use crate::{Res, ResErr};
+use crate::time;
+
+/// Used when neither VISUAL nor EDITOR are set
+const DEFAULT_EDITOR: &str = "vi";
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Marks the lines added to explain why the form was reopened
+const ERROR_PREFIX: &str = "# ERROR";
+
+/// The tasks of a group, as edited
+#[derive(Debug)]
+pub struct GroupForm {
+    pub tasks: Vec<TaskForm>
+}
+
+/// A task as edited. New tasks have no ID yet
+#[derive(Debug)]
+pub struct TaskForm {
+    pub id: Option<usize>,
+    pub name: String,
+    pub is_complete: bool,
+    pub sessions: Vec<SessionForm>,
+    pub adjustments: Vec<AdjustmentForm>,
+    pub pauses: Vec<PauseForm>
+}
+
+#[derive(Debug)]
+pub struct SessionForm {
+    pub start: i64,
+    pub end: Option<i64>,
+    pub note: Option<String>,
+    pub kind: SessionKind,
+    pub timebox: Option<Timebox>
+}
+
+#[derive(Debug)]
+pub struct PauseForm {
+    pub start: i64,
+    pub end: Option<i64>
+}
+
+#[derive(Debug)]
+pub struct AdjustmentForm {
+    pub at: i64,
+    pub seconds: i64,
+    pub note: Option<String>
+}
+
+/// Why a form could not be read, and on which line
+#[derive(Debug)]
+pub struct FormError {
+    line: usize,
+    message: String
+}
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Open the group in the editor until it is saved as a valid form.
+/// Returns None if the form was left unchanged, or emptied to cancel
+pub fn edit(group: &Group) -> Res<Option<GroupForm>> {
+    let rendered = render(group);
+    let mut text = rendered.clone();
+    // The text that last failed to parse, and why
+    let mut failed: Option<(String, FormError)> = None;
+
+    loop {
+        text = strip_errors(&open_editor(&text)?);
+
+        let is_empty = text.lines()
+            .all(|l| l.trim().is_empty() || l.trim_start().starts_with('#'));
+
+        if is_empty || text == rendered {
+            return Ok(None);
+        }
+
+        // Saved again without fixing the error, so give up rather than reopen it
+        if let Some((_, err)) = failed.as_ref().filter(|(failed, _)| *failed == text) {
+            return Err(ResErr::from(format!("Could not read the edited group, {}", err)));
+        }
+
+        match parse(&text, time::timestamp()) {
+            Ok(form) => return Ok(Some(form)),
+            Err(err) => {
+                let annotated = annotate(&text, &err);
+                failed = Some((text, err));
+                text = annotated;
+            }
+        }
+    }
+}
+
+/// Render the group as a form
+pub fn render(group: &Group) -> String {
+    let format = |stamp: i64| time::to_local_datetime(stamp).format(TIME_FORMAT).to_string();
+    let note = |note: Option<&String>| note.map(|n| format!(" | {}", n)).unwrap_or_default();
+
+    let mut text = format!("\
+# Editing group: {}
+#
+# Each task starts with a \"task <id>: <name>\" line, followed by its fields.
+# Times are local, as YYYY-MM-DD HH:MM:SS. A session ending in \"running\" is
+# still running, and only one task can be running. Notes follow a \" | \".
+# A session meant to take a set time ends in \"for <duration>\", and pomodoros
+# are \"pomodoro\" lines. Breaks are \"pause\" lines, a break that is still
+# going ends in \"running\".
+# Add a task with \"task: <name>\", or remove one by deleting its lines.
+# Save and quit to apply, or delete everything to cancel.
+", group.name());
+
+    for task in group.tasks() {
+        text += &format!("\ntask {}: {}\n", task.id(), task.name());
+        text += &format!("  complete: {}\n", if task.is_complete() { "yes" } else { "no" });
+
+        for session in task.sessions() {
+            let key = match (session.kind(), session.timebox()) {
+                (SessionKind::Logged, _) => "logged",
+                (_, Some(timebox)) if timebox.is_pomodoro() => "pomodoro",
+                _ => "session"
+            };
+            let end = session.end().map(format).unwrap_or_else(|| String::from("running"));
+            let timebox = session.timebox()
+                .map(|t| format!(" for {}", time::duration_arg(t.seconds())))
+                .unwrap_or_default();
+
+            text += &format!(
+                "  {}: {} -> {}{}{}\n", key, format(session.start()), end, timebox, note(session.note())
+            );
+        }
+
+        for pause in task.pauses() {
+            let end = pause.end().map(format).unwrap_or_else(|| String::from("running"));
+            text += &format!("  pause: {} -> {}\n", format(pause.start()), end);
+        }
+
+        for adjustment in task.adjustments() {
+            text += &format!(
+                "  adjust: {} {}{}\n",
+                format(adjustment.at()), time::duration_arg(adjustment.seconds()), note(adjustment.note())
+            );
+        }
+    }
+
+    text
+}
+
+/// Parse and validate a form.
+/// Tasks need unique IDs, sessions of a task can't overlap, and
+/// at most one task can be running.
+pub fn parse(text: &str, now: i64) -> Result<GroupForm, FormError> {
+    let mut tasks: Vec<TaskForm> = Vec::new();
+    let mut ids = HashSet::new();
+    // The line and task of the running session, if there is one
+    let mut running: Option<(usize, usize)> = None;
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let err = |message: String| FormError { line, message };
+
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        // Task header
+        if !raw.starts_with(char::is_whitespace) {
+            let header = trimmed.strip_prefix("task")
+                .ok_or_else(|| err(String::from("Expected a \"task <id>: <name>\" line")))?;
+            let colon = header.find(':')
+                .ok_or_else(|| err(String::from("Expected a \":\" after the task ID")))?;
+
+            let id = match header[..colon].trim() {
+                "" => None,
+                id => Some(id.parse::<usize>().map_err(|_| err(format!("Invalid task ID: {}", id)))?)
+            };
+
+            if let Some(id) = id {
+                if !ids.insert(id) {
+                    return Err(err(format!("Task ID {} is used more than once", id)));
+                }
+            }
+
+            let name = header[colon + 1..].trim();
+            if name.is_empty() {
+                return Err(err(String::from("A task needs a name")));
+            }
+
+            tasks.push(TaskForm {
+                id,
+                name: String::from(name),
+                is_complete: false,
+                sessions: Vec::new(),
+                adjustments: Vec::new(),
+                pauses: Vec::new()
+            });
+
+            continue;
+        }
+
+        // Task field
+        let task_index = match tasks.len() {
+            0 => return Err(err(String::from("Fields must follow a task line"))),
+            len => len - 1
+        };
+        let task = &mut tasks[task_index];
+
+        let colon = trimmed.find(':')
+            .ok_or_else(|| err(String::from("Expected \"<field>: <value>\"")))?;
+        let key = &trimmed[..colon];
+
+        let mut value_note = trimmed[colon + 1..].splitn(2, " | ");
+        let value = value_note.next().unwrap_or("").trim();
+        let note = value_note.next()
+            .map(|n| n.trim())
+            .filter(|n| !n.is_empty())
+            .map(String::from);
+
+        let parse_time = |value: &str| -> Result<i64, FormError> {
+            let stamp = time::parse_at(value, now).map_err(|e| err(e.to_string()))?;
+            if stamp > now {
+                return Err(err(format!("{} is in the future", value)));
+            }
+
+            Ok(stamp)
+        };
+
+        // A "<start> -> <end>" span, where the end can be "running"
+        let parse_span = |value: &str| -> Result<(i64, Option<i64>), FormError> {
+            let mut times = value.splitn(2, "->");
+            let start = parse_time(times.next().unwrap_or("").trim())?;
+            let end = match times.next().map(|t| t.trim()) {
+                Some("running") => None,
+                Some(end) => Some(parse_time(end)?),
+                _ => return Err(err(String::from("Expected \"<start> -> <end>\"")))
+            };
+
+            if end.map(|e| e < start).unwrap_or(false) {
+                return Err(err(String::from("It can't end before it starts")));
+            }
+
+            Ok((start, end))
+        };
+
+        match key {
+            "complete" => {
+                task.is_complete = match value {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(err(format!("complete must be yes or no, not {}", value)))
+                };
+            },
+            "pause" => {
+                let (start, end) = parse_span(value)?;
+                task.pauses.push(PauseForm { start, end });
+            },
+            "session" | "logged" | "pomodoro" => {
+                // The timebox follows the span, as "for <duration>"
+                let mut span_timebox = value.splitn(2, " for ");
+                let (start, end) = parse_span(span_timebox.next().unwrap_or("").trim())?;
+
+                let timebox = match span_timebox.next() {
+                    Some(duration) => Some(Timebox::new(
+                        time::parse_duration(duration.trim()).map_err(|e| err(e.to_string()))?,
+                        key == "pomodoro"
+                    )),
+                    _ if key == "pomodoro" => return Err(err(String::from("A pomodoro needs \"for <duration>\""))),
+                    _ => None
+                };
+                if timebox.is_some() && key == "logged" {
+                    return Err(err(String::from("A logged session can't have a timebox")));
+                }
+
+                let overlapping = task.sessions.iter()
+                    .any(|s| start < s.end.unwrap_or(now) && s.start < end.unwrap_or(now));
+                if overlapping {
+                    return Err(err(format!("Overlaps another session of {}", task.name)));
+                }
+
+                if end.is_none() {
+                    if let Some((other, _)) = running {
+                        return Err(err(format!(
+                            "Only one task can be running, the session on line {} already is", other
+                        )));
+                    }
+                    running = Some((line, task_index));
+                }
+
+                task.sessions.push(SessionForm {
+                    start,
+                    end,
+                    note,
+                    kind: if key == "logged" { SessionKind::Logged } else { SessionKind::Timed },
+                    timebox
+                });
+            },
+            "adjust" => {
+                let split = value.rfind(' ')
+                    .ok_or_else(|| err(String::from("Expected \"<time> <duration>\"")))?;
+
+                task.adjustments.push(AdjustmentForm {
+                    at: parse_time(value[..split].trim())?,
+                    seconds: time::parse_signed_duration(&value[split + 1..])
+                        .map_err(|e| err(e.to_string()))?,
+                    note
+                });
+            },
+            _ => return Err(err(format!("Unknown field: {}", key)))
+        }
+    }
+
+    // Keep the sessions of each task in order
+    for task in &mut tasks {
+        task.sessions.sort_by_key(|s| s.start);
+    }
+
+    // A complete task has been stopped
+    if let Some((line, index)) = running.filter(|(_, index)| tasks[*index].is_complete) {
+        return Err(FormError {
+            line,
+            message: format!("{} is complete, so it can't be running", tasks[index].name)
+        });
+    }
+
+    Ok(GroupForm { tasks })
+}
+
+/// Write the text to a temp file, open it in the editor and read it back
+fn open_editor(text: &str) -> Res<String> {
+    let path = env::temp_dir().join(format!("track-edit-{}.txt", process::id()));
+    fs::write(&path, text)?;
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| String::from(DEFAULT_EDITOR));
+
+    // The editor may come with arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or(DEFAULT_EDITOR);
+
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .status();
+
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    match status {
+        Ok(status) if status.success() => Ok(edited?),
+        Ok(status) => Err(ResErr::from(format!("{} exited with {}, nothing was changed", program, status))),
+        Err(err) => Err(ResErr::from(format!("Could not open editor {}: {}", program, err)))
+    }
+}
+
+/// Remove the errors added by an earlier attempt, so
+/// line numbers match the lines the user sees
+fn strip_errors(text: &str) -> String {
+    text.lines()
+        .filter(|l| !l.starts_with(ERROR_PREFIX))
+        .map(|l| format!("{}\n", l))
+        .collect()
+}
+
+/// Add the error above the line it is about, and at the top of the form
+fn annotate(text: &str, err: &FormError) -> String {
+    let mut annotated = format!("{}: {}\n", ERROR_PREFIX, err);
+
+    for (i, l) in text.lines().enumerate() {
+        if i + 1 == err.line {
+            annotated += &format!("{}: {}\n", ERROR_PREFIX, err.message);
+        }
+
+        annotated += l;
+        annotated += "\n";
+    }
+
+    annotated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> i64 {
+        time::timestamp()
+    }
+
+    /// The error line of a form that fails to parse
+    fn error_line(text: &str) -> usize {
+        parse(text, now()).unwrap_err().line
+    }
+
+    #[test]
+    fn parses_tasks_and_fields() {
+        let form = parse("\
+# A comment
+task 1: Write docs
+  complete: yes
+  session: 2020-10-15 09:00:00 -> 2020-10-15 10:00:00 | first draft
+  logged: 2020-10-15 11:00:00 -> 2020-10-15 11:30:00
+  pause: 2020-10-15 10:00:00 -> 2020-10-15 10:15:00
+  adjust: 2020-10-15 12:00:00 -15m | overcounted
+
+task: New task
+", now()).unwrap();
+
+        assert_eq!(form.tasks.len(), 2);
+
+        let task = &form.tasks[0];
+        assert_eq!(task.id, Some(1));
+        assert_eq!(task.name, "Write docs");
+        assert!(task.is_complete);
+        assert_eq!(task.sessions.len(), 2);
+        assert_eq!(task.sessions[0].end.unwrap() - task.sessions[0].start, 60 * 60);
+        assert_eq!(task.sessions[0].note.as_deref(), Some("first draft"));
+        assert_eq!(task.sessions[1].kind, SessionKind::Logged);
+        assert_eq!(task.pauses.len(), 1);
+        assert_eq!(task.adjustments[0].seconds, -15 * 60);
+
+        assert_eq!(form.tasks[1].id, None);
+        assert_eq!(form.tasks[1].name, "New task");
+    }
+
+    #[test]
+    fn parses_timeboxes_and_running_sessions() {
+        let form = parse("\
+task 1: a
+  pomodoro: 2020-10-15 09:00:00 -> 2020-10-15 09:25:00 for 25m
+  session: 2020-10-15 10:00:00 -> running for 1h
+", now()).unwrap();
+
+        let sessions = &form.tasks[0].sessions;
+        assert!(sessions[0].timebox.unwrap().is_pomodoro());
+        assert_eq!(sessions[1].timebox.unwrap().seconds(), 60 * 60);
+        assert_eq!(sessions[1].end, None);
+    }
+
+    #[test]
+    fn accepts_zero_length_spans() {
+        let form = parse("\
+task 1: a
+  session: 2020-10-15 09:00:00 -> 2020-10-15 09:00:00
+  session: 2020-10-15 09:00:00 -> 2020-10-15 10:00:00
+  pause: 2020-10-15 10:00:00 -> 2020-10-15 10:00:00
+", now()).unwrap();
+
+        assert_eq!(form.tasks[0].sessions.len(), 2);
+        assert_eq!(form.tasks[0].pauses.len(), 1);
+    }
+
+    #[test]
+    fn rejects_spans_ending_before_they_start() {
+        assert_eq!(error_line("\
+task 1: a
+  session: 2020-10-15 10:00:00 -> 2020-10-15 09:00:00
+"), 2);
+    }
+
+    #[test]
+    fn rejects_overlapping_sessions() {
+        assert_eq!(error_line("\
+task 1: a
+  session: 2020-10-15 09:00:00 -> 2020-10-15 10:00:00
+  session: 2020-10-15 09:30:00 -> 2020-10-15 10:30:00
+"), 3);
+    }
+
+    #[test]
+    fn rejects_more_than_one_running_task() {
+        assert_eq!(error_line("\
+task 1: a
+  session: 2020-10-15 09:00:00 -> running
+task 2: b
+  session: 2020-10-15 10:00:00 -> running
+"), 4);
+    }
+
+    #[test]
+    fn rejects_a_running_complete_task() {
+        assert_eq!(error_line("\
+task 1: a
+  complete: yes
+  session: 2020-10-15 09:00:00 -> running
+"), 3);
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert_eq!(error_line("task 1: a\ntask 1: b\n"), 2);
+        assert_eq!(error_line("  complete: yes\n"), 1);
+        assert_eq!(error_line("task 1:\n"), 1);
+        assert_eq!(error_line("task x: a\n"), 1);
+        assert_eq!(error_line("task 1: a\n  complete: maybe\n"), 2);
+        assert_eq!(error_line("task 1: a\n  colour: red\n"), 2);
+        assert_eq!(error_line("task 1: a\n  pomodoro: 2020-10-15 09:00:00 -> 2020-10-15 09:25:00\n"), 2);
+        assert_eq!(error_line("task 1: a\n  logged: 2020-10-15 09:00:00 -> 2020-10-15 09:25:00 for 25m\n"), 2);
+        assert_eq!(error_line("task 1: a\n  session: 2999-10-15 09:00:00 -> running\n"), 2);
+    }
+
+    #[test]
+    fn annotated_errors_are_stripped() {
+        let text = "task 1: a\n  complete: maybe\n";
+        let err = parse(text, now()).unwrap_err();
+
+        let annotated = annotate(text, &err);
+        assert!(annotated.starts_with(ERROR_PREFIX));
+        assert_eq!(strip_errors(&annotated), text);
+    }
+}
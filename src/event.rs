@@ -0,0 +1,197 @@
+///
+/// Events recorded for every change to the manager.
+/// The event log is append-only and is the source of truth:
+/// the manager's state is rebuilt by replaying it, starting from
+/// the latest snapshot.
+///
+use std::fmt;
+
+use prettytable::{Row, row};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::manager::{CarryMode, Group, Timebox};
+use crate::table::TableDisplay;
+use crate::time;
+
+/// A single change, in the order it happened
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Event {
+    seq: u64,
+    at: i64,
+
+    /// When the event was recorded, if that was after it happened.
+    /// Events logged before this was kept don't have it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recorded: Option<i64>,
+    #[serde(flatten)]
+    op: Op
+}
+
+/// The operations that change the manager.
+/// Operations refer to concrete group and task IDs, never to the
+/// "current" or "default" group, so replaying them is deterministic.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Op {
+    AddGroup { name: String },
+    UseGroup { group: Option<usize> },
+    ExtractGroups { groups: Vec<usize> },
+    InsertGroup { group: Group },
+    MinimizeIds,
+    AddTask { group: usize, name: String },
+    RemoveTask { group: usize, task: usize },
+    RenameGroup { group: usize, name: String },
+    RenameTask { group: usize, task: usize, name: String },
+    StartTask {
+        group: usize,
+        task: usize,
+        note: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timebox: Option<Timebox>
+    },
+    StopTask { group: usize, task: usize },
+    PauseTask { group: usize, task: usize },
+    CompleteTask { group: usize, task: usize },
+    LogSession { group: usize, task: usize, start: i64, end: i64, note: Option<String> },
+    AdjustTask { group: usize, task: usize, seconds: i64, note: Option<String> },
+    EditSession { group: usize, task: usize, index: usize, start: i64, end: Option<i64> },
+    RemoveSession { group: usize, task: usize, index: usize },
+    ReplaceGroup { group: Group },
+    SplitSession { group: usize, task: usize, boundary: i64, into_group: usize, into_task: usize },
+    CarryTask { group: usize, task: usize, into_group: usize, mode: CarryMode },
+    MoveTask { group: usize, task: usize, into_group: usize },
+    CopyTask { group: usize, task: usize, into_group: usize },
+
+    /// Replace the whole state, e.g. on undo, or as the first event
+    /// of data that was tracked before the event log existed
+    Restore { state: Value }
+}
+
+impl Event {
+    pub fn new(seq: u64, at: i64, recorded: i64, op: Op) -> Self {
+        let recorded = Some(recorded).filter(|r| *r != at);
+        Event { seq, at, recorded, op }
+    }
+
+    /// Read the seq of a serialized event without parsing the rest.
+    /// The seq is always serialized first, so this only reads the
+    /// start of the line
+    pub fn seq_of_line(line: &str) -> Option<u64> {
+        let rest = line.strip_prefix("{\"seq\":")?;
+        let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+
+        rest[..end].parse().ok()
+    }
+
+    // GETTERS
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn at(&self) -> i64 {
+        self.at
+    }
+
+    /// When the event was recorded, which is later than at for
+    /// backdated changes
+    pub fn recorded(&self) -> i64 {
+        self.recorded.unwrap_or(self.at)
+    }
+
+    pub fn op(&self) -> &Op {
+        &self.op
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::AddGroup { name } => write!(f, "add group {}", name),
+            Op::UseGroup { group: Some(group) } => write!(f, "use group {}", group),
+            Op::UseGroup { group: None } => write!(f, "use default group"),
+            Op::ExtractGroups { groups } => write!(f, "extract groups {:?}", groups),
+            Op::InsertGroup { group } => write!(f, "insert group {} : {}", group.id(), group.name()),
+            Op::MinimizeIds => write!(f, "minimize group IDs"),
+            Op::AddTask { group, name } => write!(f, "add task {} to group {}", name, group),
+            Op::RemoveTask { group, task } => write!(f, "remove task {} from group {}", task, group),
+            Op::RenameGroup { group, name } => write!(f, "rename group {} to {}", group, name),
+            Op::RenameTask { group, task, name } => write!(
+                f, "rename task {} in group {} to {}", task, group, name
+            ),
+            Op::StartTask { group, task, timebox: Some(timebox), .. } => write!(
+                f, "start task {} in group {} for {}{}",
+                task, group, time::duration_arg(timebox.seconds()),
+                if timebox.is_pomodoro() { " as a pomodoro" } else { "" }
+            ),
+            Op::StartTask { group, task, .. } => write!(f, "start task {} in group {}", task, group),
+            Op::StopTask { group, task } => write!(f, "stop task {} in group {}", task, group),
+            Op::PauseTask { group, task } => write!(f, "pause task {} in group {}", task, group),
+            Op::CompleteTask { group, task } => write!(f, "complete task {} in group {}", task, group),
+            Op::LogSession { group, task, start, end, .. } => write!(
+                f, "log {} on task {} in group {}", time::duration_str(end - start), task, group
+            ),
+            Op::AdjustTask { group, task, seconds, .. } => write!(
+                f, "adjust task {} in group {} by {}", task, group, time::duration_str(*seconds)
+            ),
+            Op::EditSession { group, task, index, .. } => write!(
+                f, "edit session {} of task {} in group {}", index + 1, task, group
+            ),
+            Op::RemoveSession { group, task, index } => write!(
+                f, "remove session {} of task {} in group {}", index + 1, task, group
+            ),
+            Op::ReplaceGroup { group } => write!(f, "edit group {} : {}", group.id(), group.name()),
+            Op::SplitSession { group, task, into_group, into_task, .. } => write!(
+                f, "split session of task {} in group {} into task {} in group {}",
+                task, group, into_task, into_group
+            ),
+            Op::CarryTask { group, task, into_group, mode } => write!(
+                f, "{} task {} in group {} to group {}", mode.name(), task, group, into_group
+            ),
+            Op::MoveTask { group, task, into_group } => write!(
+                f, "move task {} in group {} to group {}", task, group, into_group
+            ),
+            Op::CopyTask { group, task, into_group } => write!(
+                f, "copy task {} in group {} to group {}", task, group, into_group
+            ),
+            Op::Restore { .. } => write!(f, "restore state")
+        }
+    }
+}
+
+/// A list of events to display
+pub struct EventLog(pub Vec<Event>);
+
+// --- Table Display ---
+
+impl TableDisplay for EventLog {
+
+    fn header(&self) -> Row {
+        row!["Seq", "Time", "Event"]
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        self.0.iter()
+            .map(|e| row![
+                e.seq,
+                time::to_local_datetime(e.at).format("%B %e %r %Y"),
+                e.op
+            ])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seq_is_read_from_the_start_of_a_line() {
+        let event = Event::new(42, 10, 20, Op::AddGroup { name: String::from("a") });
+        let line = serde_json::to_string(&event).unwrap();
+
+        assert_eq!(Event::seq_of_line(&line), Some(42));
+        assert_eq!(Event::seq_of_line("{\"op\":\"minimize_ids\",\"seq\":1}"), None);
+    }
+}
@@ -1,5 +1,10 @@
-use std::path::PathBuf;
-use std::fs::{File, create_dir};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError, create_dir_all};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use serde::Serialize;
 use serde::de::DeserializeOwned;
@@ -8,25 +13,87 @@ use directories::BaseDirs;
 
 use crate::Res;
 
-const FILE_NAME: &str = "data.json";
+pub const FILE_NAME: &str = "data.json";
 
+/// Environment variable overriding the base data directory
+pub const DATA_DIR_ENV: &str = "TRACK_DATA_DIR";
+
+const LOCK_FILE_NAME: &str = "data.json.lock";
+
+/// How long to wait for another process to release the lock
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// Number of rolling backups (data.json.1 .. data.json.N) to keep
+const BACKUP_COUNT: usize = 5;
+
+/// Resolve the base directory track keeps its files in.
+/// An explicit directory (--data-dir) wins over TRACK_DATA_DIR,
+/// which wins over the platform data directory.
+pub fn base_dir(data_dir: Option<&str>) -> Res<PathBuf> {
+    if let Some(dir) = data_dir {
+        return Ok(PathBuf::from(dir));
+    }
+
+    if let Some(dir) = env::var_os(DATA_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+
+    BaseDirs::new()
+        .map(|base| base.data_dir().join(crate_name!()))
+        .ok_or_else(|| Box::from("Could not find a data directory"))
+}
+
+#[derive(Debug)]
 pub struct FileAccess {
-    path: Option<PathBuf>
+    path: Option<PathBuf>,
+    file_name: String,
+    backups: bool
+}
+
+/// An advisory lock on the data file, shared with every other track process.
+/// The lock is released when this is dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    file: File
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Clear the pid so it isn't reported once this process is gone
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
 }
 
 impl FileAccess {
-    pub fn new() -> Self {
-        if let Ok(found_path) = Self::get_or_create_dir() {
-            return FileAccess { path: Some(found_path) }
+    /// Access the data file kept in dir, creating dir if needed
+    pub fn new(dir: &Path) -> Self {
+        Self::with_file(dir, FILE_NAME)
+    }
+
+    /// Access another file kept in dir, next to the data file
+    pub fn with_file(dir: &Path, file_name: &str) -> Self {
+        let file_name = String::from(file_name);
+
+        if let Ok(found_path) = Self::get_or_create_dir(dir) {
+            return FileAccess { path: Some(found_path), file_name, backups: true }
         }
 
-        FileAccess { path: None }
+        FileAccess { path: None, file_name, backups: false }
+    }
+
+    /// Don't keep rolling backups of this file.
+    /// For files that can be rebuilt, or that are large and written often
+    pub fn without_backups(mut self) -> Self {
+        self.backups = false;
+        self
     }
 
     pub fn exists(&self) -> bool {
         match &self.path {
             Some(path_buf) => {
-                path_buf.as_path().join(FILE_NAME).exists()
+                path_buf.as_path().join(&self.file_name).exists()
             },
             _ => false
         }
@@ -36,48 +103,234 @@ impl FileAccess {
         match &self.path {
             Some(path_buf) => {
                 let path = path_buf.as_path();
-                let file_path = path.join(FILE_NAME);
+                let file_path = path.join(&self.file_name);
 
                 if file_path.exists() {
-                    let file = File::open(file_path)?;
-                    let read_val = serde_json::from_reader(file)?;
+                    let file = File::open(&file_path)?;
+                    let read_val = serde_json::from_reader(file)
+                        .map_err(|err| {
+                            let mut msg = format!("Could not read {}: {}", file_path.display(), err);
+                            if self.backups {
+                                msg += &format!(
+                                    ". Previous versions are kept as {}.1 to {}.{}",
+                                    self.file_name, self.file_name, BACKUP_COUNT
+                                );
+                            }
+
+                            msg
+                        })?;
 
                     Ok(read_val)
                 } else {
-                    return Err(Box::from("Cannot find file!"))
+                    Err(Box::from("Cannot find file!"))
                 }
             },
-            _ => return Err(Box::from("No path!"))
+            _ => Err(Box::from("No path!"))
         }
     }
 
+    /// Write the value to the data file.
+    ///
+    /// The value is written to a temp file in the same directory, synced
+    /// to disk and then renamed over the data file, so a crash part way
+    /// through can never leave a truncated data file behind.
+    /// The previous contents are kept as rolling backups.
     pub fn write<T: Serialize>(&self, val: &T) -> Res<()> {
         match &self.path {
             Some(path_buf) => {
                 let path = path_buf.as_path();
+                let file_path = path.join(&self.file_name);
+
+                let bytes = serde_json::to_vec_pretty(val)?;
 
-                let file_path = path.join(FILE_NAME);
-                let file = File::create(file_path)?;
-            
-                serde_json::to_writer_pretty(file, val)?;
-            
-                return Ok(())
+                // Nothing changed, avoid rotating the backups for no reason
+                if fs::read(&file_path).map(|b| b == bytes).unwrap_or(false) {
+                    return Ok(());
+                }
+
+                let temp_path = path.join(format!(".{}.{}.tmp", self.file_name, process::id()));
+                if let Err(err) = Self::write_synced(&temp_path, &bytes) {
+                    let _ = fs::remove_file(&temp_path);
+                    return Err(err);
+                }
+
+                if self.backups && file_path.exists() {
+                    Self::rotate_backups(path, &self.file_name)?;
+                }
+
+                fs::rename(&temp_path, &file_path)?;
+                Self::sync_dir(path)?;
+
+                Ok(())
             },
-            _ => return Err(Box::from("No path!"))
+            _ => Err(Box::from("No path!"))
         }
     }
 
-    fn get_or_create_dir() -> Res<PathBuf> {
-        if let Some(base) = BaseDirs::new() {
-            let data_dir = base.data_dir().join(crate_name!());
-    
-            if !data_dir.exists() {
-                create_dir(&data_dir)?;
+    /// Take the exclusive lock on the data file.
+    /// Waits up to LOCK_TIMEOUT for another process to release it.
+    pub fn lock(&self) -> Res<FileLock> {
+        match &self.path {
+            Some(path_buf) => {
+                let lock_path = path_buf.as_path().join(LOCK_FILE_NAME);
+                let mut file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(false)
+                    .open(&lock_path)?;
+
+                let started = Instant::now();
+                loop {
+                    match file.try_lock() {
+                        Ok(()) => break,
+                        Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => {
+                            thread::sleep(LOCK_RETRY);
+                        },
+                        Err(TryLockError::WouldBlock) => {
+                            // The holder writes its pid to the lock file
+                            let mut holder = String::new();
+                            let _ = file.read_to_string(&mut holder);
+
+                            return Err(Box::from(format!(
+                                "Timed out after {}s waiting for {}. It is held by another track process ({})",
+                                LOCK_TIMEOUT.as_secs(),
+                                lock_path.display(),
+                                if holder.trim().is_empty() { "unknown pid" } else { holder.trim() }
+                            )));
+                        },
+                        Err(TryLockError::Error(err)) => return Err(Box::from(err))
+                    }
+                }
+
+                // Record who holds the lock, to help when another process times out
+                file.set_len(0)?;
+                file.seek(SeekFrom::Start(0))?;
+                write!(file, "pid {}", process::id())?;
+
+                Ok(FileLock { file })
+            },
+            _ => Err(Box::from("No path!"))
+        }
+    }
+
+    /// Copy the file to a sibling file with the given suffix,
+    /// e.g. data.json.v0.bak. Returns the path of the copy.
+    pub fn backup(&self, suffix: &str) -> Res<PathBuf> {
+        match &self.path {
+            Some(path_buf) => {
+                let path = path_buf.as_path();
+                let backup_path = path.join(format!("{}.{}", self.file_name, suffix));
+
+                fs::copy(path.join(&self.file_name), &backup_path)?;
+
+                Ok(backup_path)
+            },
+            _ => Err(Box::from("No path!"))
+        }
+    }
+
+    /// Append lines to the end of the file, creating it if needed.
+    /// The lines are synced to disk before returning
+    pub fn append_lines(&self, lines: &[String]) -> Res<()> {
+        match &self.path {
+            Some(path_buf) => {
+                let file_path = path_buf.as_path().join(&self.file_name);
+
+                let mut file = OpenOptions::new()
+                    .read(true)
+                    .append(true)
+                    .create(true)
+                    .open(&file_path)?;
+
+                // A crash part way through an earlier append can leave a
+                // partial line. Never glue a new line onto it
+                let len = file.metadata()?.len();
+                if len > 0 {
+                    let mut last = [0u8; 1];
+                    file.seek(SeekFrom::Start(len - 1))?;
+                    file.read_exact(&mut last)?;
+
+                    if last[0] != b'\n' {
+                        file.write_all(b"\n")?;
+                    }
+                }
+
+                let mut bytes = Vec::new();
+                for line in lines {
+                    bytes.extend_from_slice(line.as_bytes());
+                    bytes.push(b'\n');
+                }
+
+                file.write_all(&bytes)?;
+                file.sync_all()?;
+
+                Ok(())
+            },
+            _ => Err(Box::from("No path!"))
+        }
+    }
+
+    /// Read every line of the file. A missing file has no lines
+    pub fn read_lines(&self) -> Res<Vec<String>> {
+        match &self.path {
+            Some(path_buf) => {
+                let file_path = path_buf.as_path().join(&self.file_name);
+                if !file_path.exists() {
+                    return Ok(Vec::new());
+                }
+
+                Ok(fs::read_to_string(file_path)?
+                    .lines()
+                    .map(String::from)
+                    .collect())
+            },
+            _ => Err(Box::from("No path!"))
+        }
+    }
+
+    /// Write the bytes to the path, and make sure they reach the disk
+    fn write_synced(path: &Path, bytes: &[u8]) -> Res<()> {
+        let mut file = File::create(path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+
+        Ok(())
+    }
+
+    /// Shift data.json.N to data.json.N+1, dropping the oldest,
+    /// and copy the current file to data.json.1
+    fn rotate_backups(dir: &Path, file_name: &str) -> Res<()> {
+        let backup = |n: usize| dir.join(format!("{}.{}", file_name, n));
+
+        for n in (1..BACKUP_COUNT).rev() {
+            if backup(n).exists() {
+                fs::rename(backup(n), backup(n + 1))?;
             }
-    
-            Ok(data_dir)
-        } else {
-            Err(Box::from("Could not create directory"))
         }
+
+        fs::copy(dir.join(file_name), backup(1))?;
+
+        Ok(())
+    }
+
+    /// Sync the directory so the rename itself is durable
+    #[cfg(unix)]
+    fn sync_dir(dir: &Path) -> Res<()> {
+        File::open(dir)?.sync_all()?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn sync_dir(_dir: &Path) -> Res<()> {
+        Ok(())
+    }
+
+    fn get_or_create_dir(dir: &Path) -> Res<PathBuf> {
+        if !dir.exists() {
+            create_dir_all(dir)?;
+        }
+
+        Ok(dir.to_path_buf())
     }
 }
@@ -0,0 +1,87 @@
+///
+/// Undo/redo history of the manager.
+/// Each entry holds how far the event log went on one side of a
+/// command, so stepping through the history replays the log up to
+/// there. Archive changes are not in the log, so entries hold the
+/// archived groups the command added or took out.
+/// 
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::time;
+
+/// Name of the document the history is stored as
+pub const DOCUMENT: &str = "history";
+
+/// How many commands can be undone
+const LIMIT: usize = 20;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct History {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>
+}
+
+/// Where the manager was on one side of a command
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Entry {
+    action: String,
+    at: i64,
+
+    /// The seq of the last event applied
+    seq: u64,
+
+    /// The archived groups that differ on the other side.
+    /// Only kept when the command changed the archive
+    archive: Option<Value>
+}
+
+impl History {
+
+    /// Record the state from before a new command.
+    /// Anything that could be redone is dropped, since it
+    /// no longer follows from the current state
+    pub fn record(&mut self, entry: Entry) {
+        self.undo.push(entry);
+        self.redo.clear();
+
+        if self.undo.len() > LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Take the entry to step back (undo) or forward (redo) to
+    pub fn pop(&mut self, undo: bool) -> Option<Entry> {
+        if undo { self.undo.pop() } else { self.redo.pop() }
+    }
+
+    /// Keep the state that was stepped away from, so the step can be reversed
+    pub fn push_reverse(&mut self, undo: bool, entry: Entry) {
+        if undo { self.redo.push(entry) } else { self.undo.push(entry) }
+    }
+}
+
+impl Entry {
+    pub fn new(action: String, seq: u64, archive: Option<Value>) -> Self {
+        Entry {
+            action,
+            at: time::timestamp(),
+            seq,
+            archive
+        }
+    }
+
+    // GETTERS
+
+    pub fn action(&self) -> &String {
+        &self.action
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn archive(&self) -> Option<&Value> {
+        self.archive.as_ref()
+    }
+}
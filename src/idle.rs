@@ -0,0 +1,120 @@
+///
+/// Find tasks that have been left running, e.g. overnight.
+/// A session is idle once it runs longer than the idle threshold,
+/// or past the end of its day.
+///
+use chrono::{offset::TimeZone, Duration, Local, Timelike};
+use prettytable::{Row, row};
+
+use crate::manager::Task;
+use crate::table::TableDisplay;
+use crate::time;
+
+/// When a running session counts as idle. Both limits are optional
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IdleLimits {
+    /// Seconds a session can run for
+    idle_after: Option<i64>,
+
+    /// Minutes into the day that the working day ends
+    day_end: Option<u32>
+}
+
+impl IdleLimits {
+    pub fn new(idle_after: Option<i64>, day_end: Option<u32>) -> Self {
+        IdleLimits { idle_after, day_end }
+    }
+
+    /// The time a session that started at start became idle, if it has by now
+    pub fn idle_since(&self, start: i64, now: i64) -> Option<i64> {
+        let after_threshold = self.idle_after.map(|seconds| start.saturating_add(seconds));
+
+        let after_day_end = self.day_end.and_then(|minutes| {
+            let mut end = time::day_of(start)
+                .naive_local()
+                .and_hms_opt(minutes / 60, minutes % 60, 0)?;
+
+            // An end before the day start hour is in the early hours of the next date
+            if end.hour() < time::day_start() {
+                end += Duration::days(1);
+            }
+
+            // An end skipped by a DST change is reached an hour later
+            Local.from_local_datetime(&end)
+                .earliest()
+                .or_else(|| Local.from_local_datetime(&(end + Duration::hours(1))).earliest())
+                .map(|t| t.timestamp())
+        });
+
+        after_threshold.into_iter()
+            .chain(after_day_end)
+            // A session started after the day end only counts from the threshold
+            .filter(|since| *since > start)
+            .min()
+            .filter(|since| *since < now)
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.idle_after.is_some() || self.day_end.is_some()
+    }
+}
+
+/// A task that has been left running
+#[derive(Debug, Clone)]
+pub struct IdleTask {
+    group: usize,
+    group_name: String,
+    task: Task,
+    since: i64
+}
+
+impl IdleTask {
+    pub fn new(group: usize, group_name: String, task: Task, since: i64) -> Self {
+        IdleTask { group, group_name, task, since }
+    }
+
+    // GETTERS
+
+    pub fn group(&self) -> usize {
+        self.group
+    }
+
+    pub fn group_name(&self) -> &String {
+        &self.group_name
+    }
+
+    pub fn task(&self) -> &Task {
+        &self.task
+    }
+
+    /// When the session became idle, which is the suggested time to cut it
+    pub fn since(&self) -> i64 {
+        self.since
+    }
+}
+
+/// A list of idle tasks to display
+pub struct IdleList(pub Vec<IdleTask>);
+
+// --- Table Display ---
+
+impl TableDisplay for IdleList {
+
+    fn header(&self) -> Row {
+        row!["Group", "Task", "Started", "Idle Since", "Time Tracked"]
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        self.0.iter()
+            .map(|idle| row![
+                format!("{} : {}", idle.group, idle.group_name),
+                format!("{} : {}", idle.task.id(), idle.task.name()),
+                idle.task.current_session()
+                    .map(|s| time::format_local(s.start()))
+                    .unwrap_or_default(),
+                time::format_local(idle.since),
+                time::duration_str(idle.task.tracked())
+            ])
+            .collect()
+    }
+}
@@ -2,7 +2,17 @@ mod common;
 pub use common::{Res, ResErr};
 
 pub mod app;
+pub mod archive;
+pub mod config;
+pub mod edit;
+pub mod event;
 pub mod file;
+pub mod history;
+pub mod idle;
 pub mod table;
 pub mod manager;
+pub mod migrate;
+pub mod profile;
+pub mod resolve;
+pub mod storage;
 pub mod time;
@@ -1,8 +1,20 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
 use std::process;
 
+use clap::ArgMatches;
+
 use track::{Res, ResErr};
 use track::app;
-use track::manager;
+use track::config::{self, Config};
+use track::edit;
+use track::event::EventLog;
+use track::file;
+use track::idle::{IdleList, IdleTask};
+use track::manager::{self, CarryMode, Manager, SessionList, Task, Timebox};
+use track::profile::{Profile, ProfileList};
+use track::storage::{self, StorageKind};
 use track::table;
 use track::time;
 
@@ -13,12 +25,249 @@ fn main() {
     }
 }
 
-fn try_main() -> Res<()> {
-    let mut manager = manager::Manager::init()?;
+/// Get a global arg, whether it was passed before or after the subcommand
+fn global_value<'a>(matches: &'a ArgMatches, name: &str) -> Option<&'a str> {
+    matches.value_of(name).or_else(|| {
+        matches.subcommand()
+            .1
+            .and_then(|sub| sub.value_of(name))
+    })
+}
+
+/// Load the data, locking it until the manager is committed
+fn open_manager(dir: &Path, config: &Config) -> Res<Manager> {
+    let mut manager = Manager::init(dir)?;
+    manager.set_action(env::args().skip(1).collect::<Vec<String>>().join(" "));
+    manager.set_split_days(config.split_days())?;
+
+    Ok(manager)
+}
+
+/// Warn about tasks left running, and offer to cut them at a chosen time.
+/// Only asks when run from a terminal, and releases the data while waiting
+/// for answers. Returns the reopened manager, and whether any task was cut
+fn check_idle(manager: Manager, dir: &Path, config: &Config) -> Res<(Manager, bool)> {
+    let idle = manager.idle_tasks(config.idle_limits());
+
+    for idle in &idle {
+        eprintln!(
+            "{} in {} has been running since {}, and is idle since {}",
+            idle.task().name(),
+            idle.group_name(),
+            idle.task().current_session().map(|s| time::format_local(s.start())).unwrap_or_default(),
+            time::format_local(idle.since())
+        );
+    }
+
+    if idle.is_empty() || !io::stdin().is_terminal() {
+        return Ok((manager, false));
+    }
+
+    // Other track commands can run while waiting for answers
+    manager.commit()?;
+
+    let mut cuts = Vec::new();
+    for idle in idle {
+        eprint!(
+            "Cut {} at (e.g. 18:00 or -2h, y for {}, empty to keep running): ",
+            idle.task().name(),
+            time::format_local(idle.since())
+        );
+        io::stderr().flush()?;
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+
+        let at = match answer.trim() {
+            "" => continue,
+            "y" | "yes" => idle.since(),
+            value => match time::parse_at(value, time::timestamp()) {
+                Ok(at) => at,
+                Err(err) => {
+                    eprintln!("{}, {} keeps running", err, idle.task().name());
+                    continue;
+                }
+            }
+        };
+
+        cuts.push((idle, at));
+    }
+
+    let mut manager = open_manager(dir, config)?;
+    let mut cut_any = false;
+
+    for (idle, at) in cuts {
+        match cut_idle(&mut manager, &idle, at) {
+            Ok(task) => {
+                println!("Cut at {}:", time::format_local(at));
+                table::display(&task);
+
+                cut_any = true;
+            },
+            Err(err) => eprintln!("{}, {} keeps running", err, idle.task().name())
+        }
+    }
+
+    Ok((manager, cut_any))
+}
+
+/// Cut an idle task, unless it was stopped or restarted while asking
+fn cut_idle(manager: &mut Manager, idle: &IdleTask, at: i64) -> Res<Task> {
+    let found = manager.find_task(&format!("{}{}", manager::UID_PREFIX, idle.task().uid()))?;
+
+    let start = |task: &Task| task.current_session().map(|s| s.start());
+    if start(&manager.task(found)?) != start(idle.task()) {
+        return Err(ResErr::from(format!("{} was stopped or restarted meanwhile", idle.task().name())));
+    }
 
+    manager.cut(found.group(), found.task(), at)
+}
+
+/// Say whether the session that was just stopped kept to its timebox.
+/// After a pomodoro, suggest the break to take, given the number of
+/// pomodoros done in the task's group
+fn report_timebox(manager: &Manager, task: &Task, config: &Config) {
+    let session = match task.sessions().last().filter(|s| !s.is_running()) {
+        Some(session) => session,
+        _ => return
+    };
+    let (timebox, overrun) = match (session.timebox(), session.overrun()) {
+        (Some(timebox), Some(overrun)) => (timebox, overrun),
+        _ => return
+    };
+
+    let length = time::duration_arg(timebox.seconds());
+    if overrun > 0 {
+        println!("Ran {} over the {} timebox", time::duration_str(overrun), length);
+    } else if overrun < 0 {
+        println!("Stopped {} into the {} timebox", time::duration_str(session.duration()), length);
+    } else {
+        println!("Finished right on the {} timebox", length);
+    }
+
+    if timebox.is_pomodoro() {
+        let pomodoros = manager.group_of(task).map(|g| g.pomodoros()).unwrap_or(0);
+        if overrun >= 0 {
+            println!(
+                "Pomodoro {} done, take a {} break",
+                pomodoros, time::duration_str(config.break_after(pomodoros))
+            );
+        } else {
+            println!("The pomodoro was cut short, so it doesn't count");
+        }
+    }
+}
+
+/// Parse an --at time, if one was passed
+fn at_value(matches: &ArgMatches, name: &str) -> Res<Option<i64>> {
+    matches.value_of(name)
+        .map(|at| time::parse_at(at, time::timestamp()))
+        .transpose()
+}
+
+fn try_main() -> Res<()> {
     // Match the input
     let matches = app::app().get_matches();
 
+    let base = file::base_dir(global_value(&matches, app::AppDataDir::name()))?;
+    let mut config = Config::load(&base)?;
+    time::set_day_start(config.day_start());
+
+    // PROFILE
+    // Handled before loading any data, since it decides which data to load
+    if let Some(sub) = matches.subcommand_matches(app::Profile::name()) {
+
+        if sub.occurrences_of(app::ProfileReset::name()) > 0 {
+            config.set_profile(None);
+            config.save(&base)?;
+        } else if let Some(name) = sub.value_of(app::ProfileValue::name()) {
+            let profile = Profile::resolve(&base, Some(name))?;
+            profile.create()?;
+
+            config.set_profile(
+                if profile.is_default() { None } else { Some(profile.name().clone()) }
+            );
+            config.save(&base)?;
+        }
+
+        let profile = Profile::resolve(&base, config.profile().map(|p| p.as_str()))?;
+        println!("Using profile: {}", profile.name());
+
+        return Ok(());
+    }
+
+    // CONFIG
+    if let Some(sub) = matches.subcommand_matches(app::Config::name()) {
+        match (sub.value_of(app::ConfigKey::name()), sub.value_of(app::ConfigValue::name())) {
+            (Some(key), Some(value)) => {
+                config.set(key, value)?;
+                config.save(&base)?;
+                println!("{}: {}", key, config.get(key)?);
+            },
+            (Some(key), _) => println!("{}: {}", key, config.get(key)?),
+            _ => table::display(&config)
+        }
+
+        return Ok(());
+    }
+
+    // The --profile flag overrides the active profile for this command only
+    let profile = Profile::resolve(
+        &base,
+        global_value(&matches, app::AppProfile::name())
+            .or_else(|| config.profile().map(|p| p.as_str()))
+    )?;
+
+    // PROFILES
+    if matches.subcommand_matches(app::Profiles::name()).is_some() {
+        table::display(&ProfileList::load(&base, &profile)?);
+        return Ok(());
+    }
+
+    // MIGRATE STORAGE
+    if let Some(sub) = matches.subcommand_matches(app::MigrateStorage::name()) {
+        let to = sub.value_of(app::MigrateStorageValue::name())
+            .unwrap()
+            .parse::<StorageKind>()?;
+
+        let from = storage::convert(profile.dir(), to)?;
+        println!("Migrated storage: {} -> {}", from.name(), to.name());
+
+        return Ok(());
+    }
+
+    let mut manager = open_manager(profile.dir(), &config)?;
+
+    if let Some(today) = manager.set_carry_over(config.carry_over())? {
+        println!("Carried unfinished tasks over to {}:", today.name());
+        table::display(&today);
+    }
+
+    // CHECK
+    // For scripts, so it never asks and exits non-zero when a task is idle
+    if matches.subcommand_matches(app::Check::name()).is_some() {
+        let idle = manager.idle_tasks(config.idle_limits());
+
+        if !config.idle_limits().is_set() {
+            println!("No idle limits are set, see track config {} and {}", config::IDLE_AFTER, config::DAY_END);
+        } else if idle.is_empty() {
+            println!("No idle tasks");
+        } else {
+            println!("Idle:");
+            table::display(&IdleList(idle));
+
+            // Save anything carried over, which also releases the data
+            manager.commit()?;
+            process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    // Stopping a task that was just cut would fail, there is nothing left to stop
+    let (mut manager, cut_any) = check_idle(manager, profile.dir(), &config)?;
+    let cut = cut_any && manager.group()?.current_task().is_none();
+
     // NEW
     if let Some(sub) = matches.subcommand_matches(app::New::name()) {
         // Can use unwrap because it is required
@@ -34,27 +283,90 @@ fn try_main() -> Res<()> {
 
     // REMOVE
     else if let Some(sub) = matches.subcommand_matches(app::Remove::name()) {
-        let id = sub.value_of(app::RemoveValue::name())
-            .unwrap()
-            .parse::<usize>()?;
+        let task = manager.find_task_strict(sub.value_of(app::RemoveValue::name()).unwrap())?;
 
-        let removed_task = manager.remove_task(id)?;
+        let removed_task = manager.remove_task(task)?;
 
         // Display
         println!("Removed:");
         table::display(&removed_task);
     }
 
+    // RENAME
+    else if let Some(sub) = matches.subcommand_matches(app::Rename::name()) {
+        // Can use unwrap because they are required
+        let task = manager.find_task(sub.value_of(app::RenameValue::name()).unwrap())?;
+        let name = sub.value_of(app::RenameName::name()).unwrap();
+
+        let renamed_task = manager.rename_task(task, name.to_owned())?;
+
+        // Display
+        println!("Renamed:");
+        table::display(&renamed_task);
+    }
+
+    // MOVE / COPY
+    else if let Some(sub) = matches.subcommand_name()
+        .filter(|sub| *sub == app::Move::name() || *sub == app::Copy::name()) {
+
+        let is_move = sub == app::Move::name();
+        let sub = matches.subcommand_matches(sub).unwrap();
+
+        // Can use unwrap because they are required
+        let (value, to) = if is_move {
+            (app::MoveValue::name(), app::MoveTo::name())
+        } else {
+            (app::CopyValue::name(), app::CopyTo::name())
+        };
+        let task = manager.find_task(sub.value_of(value).unwrap())?;
+        let into = manager.find_group(sub.value_of(to).unwrap())?;
+
+        let task = if is_move { manager.move_task(task, into)? } else { manager.copy_task(task, into)? };
+
+        // Display
+        println!("{} to {}:", if is_move { "Moved" } else { "Copied" }, manager.group_with_id(into)?.name());
+        table::display(&task);
+    }
+
     // TASKS
-    else if let Some(_) = matches.subcommand_matches(app::Tasks::name()) {
-        let group = manager.group()?;
-        println!("{}:", group.name());
-        table::display(group);
+    else if let Some(sub) = matches.subcommand_matches(app::Tasks::name()) {
+        if let Some(as_of) = sub.value_of(app::TasksAsOf::name()) {
+            let mut past = manager.as_of(time::parse_local(as_of)?)?;
+            let group = past.group()
+                .map_err(|_| ResErr::from(format!("There was no group as of {}", as_of)))?;
+            println!("{} (as of {}):", group.name(), as_of);
+            table::display(group);
+        } else {
+            let group = manager.group()?;
+            println!("{}:", group.name());
+            table::display(group);
+        }
     }
 
     // GROUPS
-    else if let Some(_) = matches.subcommand_matches(app::Groups::name()) {
-        table::display(&manager);
+    else if let Some(sub) = matches.subcommand_matches(app::Groups::name()) {
+        if let Some(as_of) = sub.value_of(app::GroupsAsOf::name()) {
+            let past = manager.as_of(time::parse_local(as_of)?)?;
+            println!("As of {}:", as_of);
+            table::display(&past);
+        } else {
+            table::display(&manager);
+        }
+    }
+
+    // GROUP
+    else if let Some(sub) = matches.subcommand_matches(app::Group::name()) {
+
+        if let Some(rename) = sub.subcommand_matches(app::GroupRename::name()) {
+            // Can use unwrap because they are required
+            let id = manager.find_group(rename.value_of(app::GroupRenameValue::name()).unwrap())?;
+            let name = rename.value_of(app::GroupRenameName::name()).unwrap();
+
+            let group = manager.rename_group(id, name.to_owned())?;
+            println!("Renamed group: {} : {}", group.id(), group.name());
+        } else {
+            return Err(ResErr::from("Use track group rename"));
+        }
     }
 
     // USE
@@ -62,13 +374,13 @@ fn try_main() -> Res<()> {
         
         if sub.occurrences_of(app::UseReset::name()) > 0 {
             // If we want to reset the used group, it will make it whatever today is
-            manager.reset_group();
+            manager.reset_group()?;
             println!("Resetting group...")
         } else {
-            let id = sub.value_of(app::UseValue::name())
-                .ok_or(ResErr::from("Invalid command"))?
-                .parse::<usize>()?;
+            let value = sub.value_of(app::UseValue::name())
+                .ok_or(ResErr::from("Invalid command"))?;
 
+            let id = manager.find_group(value)?;
             manager.use_group(id)?;
         }
 
@@ -79,26 +391,227 @@ fn try_main() -> Res<()> {
     // START
     else if let Some(sub) = matches.subcommand_matches(app::Start::name()) {
         // Can use unwrap because it is required
-        let id = sub.value_of(app::StartValue::name())
-            .unwrap()
-            .parse::<usize>()?;
+        let task = manager.find_task(sub.value_of(app::StartValue::name()).unwrap())?;
+
+        let note = sub.value_of(app::StartNote::name()).map(String::from);
+
+        let at = at_value(sub, app::StartAt::name())?;
+
+        let parallel = sub.occurrences_of(app::StartParallel::name()) > 0;
+
+        let timebox = sub.value_of(app::StartFor::name())
+            .map(time::parse_duration)
+            .transpose()?
+            .map(|seconds| Timebox::new(seconds, false));
+        if timebox.map(|t| t.seconds() <= 0).unwrap_or(false) {
+            return Err(ResErr::from("A timebox must be longer than zero"));
+        }
+
+        let (started_task, stopped) = manager.start_task(task, note, timebox, at, parallel)?;
+
+        // Only one task runs at a time, unless running in parallel
+        for task in stopped {
+            println!("Stopping:");
+            table::display(&task);
+            report_timebox(&manager, &task, &config);
+        }
 
-        let started_task = manager.start_task(id)?;
+        match timebox {
+            Some(timebox) => println!("Starting for {}:", time::duration_str(timebox.seconds())),
+            _ => println!("Starting:")
+        }
+        table::display(&started_task);
+    }
+
+    // POMODORO
+    else if let Some(sub) = matches.subcommand_matches(app::Pomodoro::name()) {
+        // Can use unwrap because it is required
+        let task = manager.find_task(sub.value_of(app::PomodoroValue::name()).unwrap())?;
+
+        let note = sub.value_of(app::PomodoroNote::name()).map(String::from);
+        let parallel = sub.occurrences_of(app::PomodoroParallel::name()) > 0;
 
-        println!("Starting:");
+        let timebox = Timebox::new(config.pomodoro(), true);
+        let (started_task, stopped) = manager.start_task(task, note, Some(timebox), None, parallel)?;
+
+        for task in stopped {
+            println!("Stopping:");
+            table::display(&task);
+            report_timebox(&manager, &task, &config);
+        }
+
+        let pomodoros = manager.group_of(&started_task).map(|g| g.pomodoros()).unwrap_or(0);
+        println!(
+            "Starting pomodoro {} for {}, then take a {} break:",
+            pomodoros + 1,
+            time::duration_str(timebox.seconds()),
+            time::duration_str(config.break_after(pomodoros + 1))
+        );
         table::display(&started_task);
     }
 
     // STOP
-    else if let Some(_) = matches.subcommand_matches(app::Stop::name()) {
-        let stopped_task = manager.stop_current()?;
+    else if let Some(sub) = matches.subcommand_matches(app::Stop::name()).filter(|_| !cut) {
+        let at = at_value(sub, app::StopAt::name())?;
+        let stopped_task = manager.stop_current(at)?;
 
         println!("Stopping:");
         table::display(&stopped_task);
+        report_timebox(&manager, &stopped_task, &config);
+    }
+
+    // LOG
+    else if let Some(sub) = matches.subcommand_matches(app::Log::name()) {
+        // Can use unwrap because they are required
+        let task = manager.find_task(sub.value_of(app::LogValue::name()).unwrap())?;
+        let seconds = time::parse_duration(sub.value_of(app::LogDuration::name()).unwrap())?;
+
+        let at = at_value(sub, app::LogAt::name())?;
+        let note = sub.value_of(app::LogNote::name()).map(String::from);
+
+        let task = manager.log_time(task, seconds, at, note)?;
+
+        println!("Logged {}:", time::duration_str(seconds));
+        table::display(&task);
+    }
+
+    // ADJUST
+    else if let Some(sub) = matches.subcommand_matches(app::Adjust::name()) {
+        // Can use unwrap because they are required
+        let task = manager.find_task(sub.value_of(app::AdjustValue::name()).unwrap())?;
+        let seconds = time::parse_signed_duration(sub.value_of(app::AdjustDuration::name()).unwrap())?;
+
+        let note = sub.value_of(app::AdjustNote::name()).map(String::from);
+
+        let task = manager.adjust_time(task, seconds, note)?;
+
+        println!("Adjusted by {}:", time::duration_str(seconds));
+        table::display(&task);
+    }
+
+    // SESSIONS
+    else if let Some(sub) = matches.subcommand_matches(app::Sessions::name()) {
+        // Can use unwrap because it is required
+        let task_ref = manager.find_task(sub.value_of(app::SessionsValue::name()).unwrap())?;
+
+        let task = manager.task(task_ref)?;
+        println!("{} has the unique ID {}{}", task.name(), manager::UID_PREFIX, task.uid());
+        table::display(&task);
+
+        let (total, days) = manager.tracked_across_days(task_ref)?;
+        if days > 1 {
+            println!("Tracked across {} days: {}", days, time::duration_str(total));
+        }
+
+        table::display(&SessionList(task));
+    }
+
+    // SESSION
+    else if let Some(sub) = matches.subcommand_matches(app::Session::name()) {
+
+        let task = if let Some(edit) = sub.subcommand_matches(app::SessionEdit::name()) {
+            // Can use unwrap because they are required
+            let task = manager.find_task(edit.value_of(app::SessionEditValue::name()).unwrap())?;
+            let number = edit.value_of(app::SessionEditNumber::name())
+                .unwrap()
+                .parse::<usize>()?;
+
+            let start = at_value(edit, app::SessionEditStart::name())?;
+            let end = at_value(edit, app::SessionEditEnd::name())?;
+            if start.is_none() && end.is_none() {
+                return Err(ResErr::from("Pass --start and/or --end to edit the session"));
+            }
+
+            let task = manager.edit_session(task, number, start, end)?;
+            println!("Edited session {}:", number);
+
+            task
+        } else if let Some(rm) = sub.subcommand_matches(app::SessionRemove::name()) {
+            // Can use unwrap because they are required
+            let task = manager.find_task_strict(rm.value_of(app::SessionRemoveValue::name()).unwrap())?;
+            let number = rm.value_of(app::SessionRemoveNumber::name())
+                .unwrap()
+                .parse::<usize>()?;
+
+            let task = manager.remove_session(task, number)?;
+            println!("Removed session {}:", number);
+
+            task
+        } else {
+            return Err(ResErr::from("Use track session edit or track session rm"));
+        };
+
+        table::display(&task);
+        table::display(&SessionList(task));
+    }
+
+    // EDIT
+    else if let Some(sub) = matches.subcommand_matches(app::Edit::name()) {
+        let group = match sub.value_of(app::EditValue::name()) {
+            Some(value) => {
+                let id = manager.find_group(value)?;
+                manager.group_with_id(id)?.clone()
+            },
+            _ => manager.group()?.clone()
+        };
+
+        // Other track commands can run while the editor is open
+        manager.commit()?;
+        let edited = edit::edit(&group)?;
+        manager = open_manager(profile.dir(), &config)?;
+
+        let id = manager.find_group(&format!("{}{}", manager::UID_PREFIX, group.uid()))?;
+        if serde_json::to_value(manager.group_with_id(id)?)? != serde_json::to_value(&group)? {
+            return Err(ResErr::from(format!(
+                "{} was changed while it was being edited, nothing was saved", group.name()
+            )));
+        }
+
+        match edited {
+            Some(form) => {
+                let (edited, stopped) = manager.replace_tasks(id, form)?;
+                println!("Edited {}:", edited.name());
+                table::display(&edited);
+
+                for task in stopped {
+                    println!("Stopping:");
+                    table::display(&task);
+                    report_timebox(&manager, &task, &config);
+                }
+            },
+            _ => println!("No changes made")
+        }
+    }
+
+    // PAUSE
+    else if let Some(sub) = matches.subcommand_matches(app::Pause::name()).filter(|_| !cut) {
+        let at = at_value(sub, app::PauseAt::name())?;
+        let paused_task = manager.pause(at)?;
+
+        println!("Pausing:");
+        table::display(&paused_task);
+        report_timebox(&manager, &paused_task, &config);
+    }
+
+    // RESUME
+    else if let Some(sub) = matches.subcommand_matches(app::Resume::name()) {
+        let at = at_value(sub, app::ResumeAt::name())?;
+        let parallel = sub.occurrences_of(app::ResumeParallel::name()) > 0;
+
+        let (resumed_task, length, stopped) = manager.resume(at, parallel)?;
+
+        for task in stopped {
+            println!("Stopping:");
+            table::display(&task);
+            report_timebox(&manager, &task, &config);
+        }
+
+        println!("Resuming after a {} break:", time::duration_str(length));
+        table::display(&resumed_task);
     }
 
     // TOMORROW
-    else if let Some(_) = matches.subcommand_matches(app::Tomorrow::name()) {
+    else if let Some(sub) = matches.subcommand_matches(app::Tomorrow::name()) {
         // Get the tomorrow name, which we will use as the new group name.
         let tomorrow = time::tomorrow_local()
             .format(manager::DATE_FORMAT)
@@ -111,25 +624,46 @@ fn try_main() -> Res<()> {
         
         manager.use_group(group.id())?;
         println!("Using group: {}", group_name);
+
+        // The flag overrides the carry-over setting
+        let carry = sub.value_of(app::TomorrowCarry::name())
+            .map(|mode| mode.parse::<CarryMode>())
+            .transpose()?
+            .or_else(|| config.carry_over());
+
+        if let Some(mode) = carry {
+            if !manager.carry_tasks(group.id(), mode)?.is_empty() {
+                println!("Carried unfinished tasks over to {}:", group_name);
+                table::display(manager.group_with_id(group.id())?);
+            }
+        }
     }
 
     // COMPLETE
     else if let Some(sub) = matches.subcommand_matches(app::Complete::name()) {
-        
+        let at = at_value(sub, app::CompleteAt::name())?;
+
         // If we want to process current, do that
         if sub.occurrences_of(app::CompleteCurrent::name()) > 0 {
-            let task = manager.complete_task(None)?;
+            let task = manager.complete_task(None, at)?;
             println!("Completed curent:");
             table::display(&task);
+            report_timebox(&manager, &task, &config);
         } else {
             // Otherwise an ID should have been passed or it was an invalid command
-            let id = sub.value_of(app::CompleteValue::name())
-                .ok_or(ResErr::from("Invalid command"))?
-                .parse::<usize>()?;
+            let value = sub.value_of(app::CompleteValue::name())
+                .ok_or(ResErr::from("Invalid command"))?;
+            let task_ref = manager.find_task(value)?;
+
+            // Only a running task has a session to report on
+            let was_running = manager.task(task_ref)?.current_session().is_some();
 
-            let task = manager.complete_task(Some(id))?;
+            let task = manager.complete_task(Some(task_ref), at)?;
             println!("Completed:");
             table::display(&task);
+            if was_running {
+                report_timebox(&manager, &task, &config);
+            }
         }
     }
 
@@ -144,14 +678,70 @@ fn try_main() -> Res<()> {
             .collect();
 
         let mut parsed_ids: Vec<usize> = Vec::new();
-        for val in split { parsed_ids.push(val.parse::<usize>()?); }
+        for val in split { parsed_ids.push(manager.find_group_strict(val)?); }
+
+        let archived = manager.archive_groups(retain, parsed_ids)?;
+        for (archive_id, g) in archived {
+            println!("Archiving: {} : {} (archive ID {})", g.id(), g.name(), archive_id)
+        }
+
+        manager.minimize_ids()?;
+    }
+
+    // ARCHIVED
+    else if let Some(sub) = matches.subcommand_matches(app::Archived::name()) {
+        let archive = manager.archive()?;
+
+        if let Some(show) = sub.value_of(app::ArchivedShow::name()) {
+            let archived = archive.get(show.parse::<usize>()?)
+                .ok_or_else(|| ResErr::from("Could not find archived group!"))?;
+
+            println!("{} (archived):", archived.group().name());
+            table::display(archived.group());
+        } else if let Some(query) = sub.value_of(app::ArchivedValue::name()) {
+            let found = archive.search(query);
+            if found.is_empty() {
+                println!("No archived groups match: {}", query);
+            } else {
+                table::display(&found);
+            }
+        } else {
+            table::display(archive);
+        }
+    }
 
-        let extracted = manager.extract_groups(retain, parsed_ids)?;
-        for g in extracted {
-            println!("Archving: {} : {}", g.id(), g.name())
+    // UNARCHIVE
+    else if let Some(sub) = matches.subcommand_matches(app::Unarchive::name()) {
+        let id = sub.value_of(app::UnarchiveValue::name())
+            .unwrap()
+            .parse::<usize>()?;
+
+        let group = manager.unarchive(id)?;
+        println!("Restored group: {} : {}", group.id(), group.name());
+    }
+
+    // UNDO / REDO
+    else if let Some(sub) = matches.subcommand_name()
+        .filter(|sub| *sub == app::Undo::name() || *sub == app::Redo::name()) {
+
+        let is_undo = sub == app::Undo::name();
+        let (action, changed) = if is_undo { manager.undo()? } else { manager.redo()? };
+
+        println!("{}: {}", if is_undo { "Undid" } else { "Redid" }, action);
+
+        if changed.is_empty() {
+            table::display(&manager);
         }
 
-        manager.minimize_ids();
+        for group in changed {
+            println!("{}:", group.name());
+            table::display(&group);
+        }
+    }
+
+    // EVENTS
+    else if matches.subcommand_matches(app::Events::name()).is_some() {
+        table::display(&EventLog(manager.events()?));
     }
 
     manager.commit()
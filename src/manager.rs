@@ -1,20 +1,43 @@
 ///
-/// Manager should be used to interfact directly with the data file
+/// Manager should be used to interfact directly with the stored data
 /// to perform all core project actions.
 /// 
 use std::cmp;
+use std::path::Path;
+use std::str::FromStr;
 
 use serde::{Serialize, Deserialize};
-use prettytable::{Attr, color, Cell, Row, row, cell};
-
-use crate::file::FileAccess;
+use serde_json::Value;
+use chrono::NaiveDate;
+use prettytable::{Attr, color, Cell, Row, row};
+
+use crate::archive::Archive;
+use crate::edit::GroupForm;
+use crate::event::{Event, Op};
+use crate::history::{self, Entry, History};
+use crate::idle::{IdleLimits, IdleTask};
+use crate::migrate;
+use crate::resolve::{self, Found, Matching};
+use crate::storage::{self, Storage};
 use crate::{Res, ResErr};
 use crate::table::TableDisplay;
 use crate::time;
 
 pub const DATE_FORMAT: &str = "%m-%d-%Y";
 
-/// Get the name of the default group, being the local date of today
+/// Marks a unique ID where a task or group is referred to, e.g. "@12"
+pub const UID_PREFIX: &str = "@";
+
+/// The error for a name that matches several tasks or groups,
+/// listing each of them
+fn ambiguous(kind: &str, value: &str, candidates: Vec<String>) -> ResErr {
+    ResErr::from(format!(
+        "{} matches more than one {}, use one of these IDs:\n  {}", value, kind, candidates.join("\n  ")
+    ))
+}
+
+/// Get the name of the default group, being the local date of today.
+/// Today only ends at the day start hour, see time::set_day_start
 /// 
 /// The value returned from this method should be unique. The uniqueness
 /// of this value will NOT be enforced elsewhere.
@@ -25,44 +48,495 @@ fn default_group_name() -> String {
 
 // --- DATA STRUCTS ---
 
+/// How many events to replay on load before writing a new snapshot
+const SNAPSHOT_INTERVAL: u64 = 50;
+
 /// Manages groups of tasks
+///
+/// Every change is made by applying an event, which is appended to the
+/// event log on commit. The serialized manager is a snapshot of the
+/// state after the event numbered event_seq.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Manager {
+    version: u64,
+    event_seq: u64,
     next_group: usize,
+
+    /// The next unique ID for a group or task, see Group::uid
+    #[serde(default)]
+    next_uid: u64,
     current_group: Option<usize>,
-    groups: Vec<Group>
+    groups: Vec<Group>,
+
+    /// The task taking a break, to resume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    paused: Option<Paused>,
+
+    /// Where this manager was loaded from, and is committed to
+    #[serde(skip)]
+    storage: Option<Box<dyn Storage>>,
+
+    /// Events applied since loading, not yet in the log
+    #[serde(skip)]
+    pending: Vec<Event>,
+
+    /// The event_seq of the snapshot this was loaded from, if any
+    #[serde(skip)]
+    snapshot_seq: Option<u64>,
+
+    /// The archived groups. Only loaded from storage when needed
+    #[serde(skip)]
+    archive: Option<Archive>,
+
+    #[serde(skip)]
+    archive_changed: bool,
+
+    /// The event_seq as loaded, and the archive as loaded (if it was).
+    /// Kept so commit can record what to undo back to
+    #[serde(skip)]
+    loaded_seq: Option<u64>,
+
+    #[serde(skip)]
+    archive_loaded: Option<Archive>,
+
+    /// Describes the change being made, for the undo history
+    #[serde(skip)]
+    action: Option<String>,
+
+    /// Stepping through the history shouldn't record a new step
+    #[serde(skip)]
+    record_history: bool,
+
+    /// Split sessions crossing the day start into the next day's group
+    #[serde(skip)]
+    split_days: bool,
+
+    /// The default group, if it was only added when loading
+    #[serde(skip)]
+    new_day: Option<usize>
 }
 
 /// INIT
 impl Manager {
 
-    pub fn init() -> Res<Manager> {
-        let file_access = FileAccess::new();
+    /// Load the manager from the data stored in dir
+    pub fn init(dir: &Path) -> Res<Manager> {
+        Self::with_storage(storage::open(dir)?)
+    }
+
+    /// Load the manager from any storage backend.
+    /// Starts from the latest snapshot, if there is one, and replays
+    /// every event logged after it. An empty storage starts out
+    /// with a new manager
+    pub fn with_storage(mut storage: Box<dyn Storage>) -> Res<Manager> {
+        let snapshot = storage.load()?;
+        let has_snapshot = snapshot.is_some();
+
+        let mut manager = snapshot.unwrap_or_else(Manager::new);
+        if has_snapshot {
+            manager.snapshot_seq = Some(manager.event_seq);
+        }
+
+        let events = storage.load_events(manager.event_seq)?;
+        let log_is_empty = manager.event_seq == 0 && events.is_empty();
 
-        // Ensure the file exists
-        if !file_access.exists() {
-            file_access.write(&Manager::new())?;
+        for event in &events {
+            manager.apply(event)?;
         }
 
-        let mut manager: Manager = file_access.read()?;
+        manager.storage = Some(storage);
+
+        // Data tracked before the event log existed becomes its first
+        // event, so the log can always be replayed from the start
+        if has_snapshot && log_is_empty {
+            let state = serde_json::to_value(&manager)?;
+            manager.emit(Op::Restore { state })?;
+        }
 
         // Ensure that there is a default group
-        let res = manager.add_group(default_group_name());
-        if res.is_ok() {
-            file_access.write(&manager)?;
+        let name = default_group_name();
+        if manager.group_by_name(&name).is_none() {
+            manager.new_day = Some(manager.add_group(name)?.id);
         }
 
+        manager.loaded_seq = Some(manager.event_seq);
+        manager.record_history = true;
+
         Ok(manager)
     }
 
-    pub fn commit(&self) -> Res<()> {
-        let file_access = FileAccess::new();
-        file_access.write(self)?;
+    /// Save the manager to its storage.
+    /// This ends the unit of work, releasing anything the storage held on to
+    pub fn commit(mut self) -> Res<()> {
+        let mut storage = self.storage.take()
+            .ok_or_else(|| ResErr::from("Manager has no storage to commit to"))?;
+
+        if self.record_history {
+            self.record(storage.as_mut())?;
+        }
+
+        // Save the archive first. If saving the manager then fails, the
+        // archived groups are in both places rather than in neither
+        if let Some(archive) = self.archive.as_ref().filter(|_| self.archive_changed) {
+            storage.save_archive(archive)?;
+        }
+
+        storage.append_events(&self.pending)?;
+
+        // The log is the source of truth, the snapshot only
+        // needs to be written every so often to keep loading fast
+        let snapshot_due = self.snapshot_seq
+            .map(|seq| self.event_seq - seq >= SNAPSHOT_INTERVAL)
+            .unwrap_or(true);
+
+        if snapshot_due {
+            storage.save(&self)?;
+        }
+
+        Ok(())
+    }
+
+    /// Describe the change being made, shown when it is undone
+    pub fn set_action(&mut self, action: String) {
+        self.action = Some(action);
+    }
+
+    /// Split sessions that cross the day start, so each day's group only holds
+    /// the time worked on that day. Running sessions are split right away
+    pub fn set_split_days(&mut self, split_days: bool) -> Res<()> {
+        self.split_days = split_days;
+
+        let running: Vec<(usize, usize)> = self.groups.iter()
+            .filter_map(|g| g.current_task.map(|t| (g.id, t)))
+            .collect();
+
+        for (group, task) in running {
+            self.split_across_days(group, task)?;
+        }
+
+        Ok(())
+    }
+
+    /// Carry unfinished tasks into today's group, if it was only just added.
+    /// Returns today's group if any tasks were carried over
+    pub fn set_carry_over(&mut self, carry_over: Option<CarryMode>) -> Res<Option<Group>> {
+        let (mode, group) = match (carry_over, self.new_day) {
+            (Some(mode), Some(group)) => (mode, group),
+            _ => return Ok(None)
+        };
+
+        if self.carry_tasks(group, mode)?.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(self.group_mut(group)?.clone()))
+    }
+}
+
+/// EVENTS
+impl Manager {
+
+    /// Every event in the log, including those not committed yet
+    pub fn events(&mut self) -> Res<Vec<Event>> {
+        let mut events = self.storage_mut()?.load_events(0)?;
+        events.extend(self.pending.iter().cloned());
+
+        Ok(events)
+    }
+
+    /// Rebuild the manager as it was at the given time, by replaying
+    /// the log from the start. The result is detached from storage
+    pub fn as_of(&mut self, at: i64) -> Res<Manager> {
+        let mut past = Manager::new();
+
+        // Each event may depend on those recorded before it, so replay
+        // the log up to the first one recorded later, even if that one
+        // was backdated to before the time
+        for event in self.events()?.iter().take_while(|e| e.recorded() <= at) {
+            past.apply(event)?;
+        }
+
+        // Show the group of that day, unless another group was in use
+        if past.current_group.is_none() {
+            let name = time::day_of(at).format(DATE_FORMAT).to_string();
+            past.current_group = past.group_by_name(&name).map(|g| g.id);
+        }
+
+        Ok(past)
+    }
+
+    /// Apply a new event and queue it for the log
+    fn emit(&mut self, op: Op) -> Res<()> {
+        self.emit_at(op, time::timestamp())
+    }
+
+    /// Apply a new event that happened at the given time, which may be
+    /// in the past, and queue it for the log
+    fn emit_at(&mut self, op: Op, at: i64) -> Res<()> {
+        let event = Event::new(self.event_seq + 1, at, time::timestamp(), op);
+
+        self.apply(&event)?;
+        self.pending.push(event);
+
+        Ok(())
+    }
+
+    /// Apply an event to the state.
+    /// Everything that could depend on when the event is applied has
+    /// already been resolved into the event, so this is deterministic.
+    fn apply(&mut self, event: &Event) -> Res<()> {
+        let at = event.at();
+
+        match event.op() {
+            Op::AddGroup { name } => {
+                let group = Group::new(self.next_group, self.take_uid(), name.clone());
+
+                self.next_group += 1;
+                self.groups.push(group);
+            },
+            Op::UseGroup { group } => {
+                if let Some(id) = group {
+                    self.group_mut(*id)?;
+                }
+
+                self.current_group = *group;
+            },
+            Op::ExtractGroups { groups } => {
+                self.groups.retain(|g| !groups.contains(&g.id));
+
+                if self.paused.map(|p| groups.contains(&p.group)).unwrap_or(false) {
+                    self.paused = None;
+                }
+            },
+            Op::InsertGroup { group } => {
+                self.next_group = cmp::max(self.next_group, group.id + 1);
+                self.groups.push(group.clone());
+
+                // Groups archived before unique IDs existed get theirs now
+                self.assign_uids();
+            },
+            Op::MinimizeIds => self.minimize(),
+            Op::AddTask { group, name } => {
+                let uid = self.take_uid();
+                self.group_mut(*group)?.add_task(uid, name.clone());
+            },
+            Op::RenameGroup { group, name } => {
+                self.group_mut(*group)?.name = name.clone();
+            },
+            Op::RenameTask { group, task, name } => {
+                self.group_task_mut(*group, *task)?.name = name.clone();
+            },
+            Op::RemoveTask { group, task } => {
+                self.group_mut(*group)?.remove_task(*task)?;
+
+                if self.paused == Some(Paused { group: *group, task: *task }) {
+                    self.paused = None;
+                }
+            },
+            Op::StartTask { group, task, note, timebox } => {
+                // Getting back to work ends the break
+                self.end_break(at)?;
+                self.group_mut(*group)?.start_task(*task, note.clone(), *timebox, at)?;
+            },
+            Op::PauseTask { group, task } => {
+                self.end_break(at)?;
+                self.group_mut(*group)?.stop_task(*task, at)?;
+                self.group_task_mut(*group, *task)?.pauses.push(Pause { start: at, end: None });
+
+                self.paused = Some(Paused { group: *group, task: *task });
+            },
+            Op::StopTask { group, task } => {
+                self.group_mut(*group)?.stop_task(*task, at)?;
+            },
+            Op::CompleteTask { group, task } => {
+                if self.paused == Some(Paused { group: *group, task: *task }) {
+                    self.end_break(at)?;
+                }
+
+                self.group_mut(*group)?.complete_task(*task, at)?;
+            },
+            Op::LogSession { group, task, start, end, note } => {
+                self.group_task_mut(*group, *task)?.log(*start, *end, note.clone());
+            },
+            Op::EditSession { group, task, index, start, end } => {
+                self.group_task_mut(*group, *task)?.edit_session(*index, *start, *end)?;
+            },
+            Op::RemoveSession { group, task, index } => {
+                self.group_mut(*group)?.remove_session(*task, *index)?;
+            },
+            Op::SplitSession { group, task, boundary, into_group, into_task } => {
+                self.split_session(*group, *task, *boundary, *into_group, *into_task)?;
+            },
+            Op::CarryTask { group, task, into_group, mode } => {
+                self.carry_task(*group, *task, *into_group, *mode)?;
+            },
+            Op::MoveTask { group, task, into_group } => {
+                self.transfer_task(*group, *task, *into_group, true)?;
+            },
+            Op::CopyTask { group, task, into_group } => {
+                self.transfer_task(*group, *task, *into_group, false)?;
+            },
+            Op::ReplaceGroup { group } => {
+                *self.group_mut(group.id)? = group.clone();
+
+                // Tasks added by hand get their unique IDs here
+                self.assign_uids();
+
+                // The break is over if its task, or the break itself, was removed
+                let on_break = |p: &Paused| group.task(p.task)
+                    .map(|t| t.pauses.iter().any(|p| p.end.is_none()))
+                    .unwrap_or(false);
+
+                if self.paused.filter(|p| p.group == group.id && !on_break(p)).is_some() {
+                    self.paused = None;
+                }
+            },
+            Op::AdjustTask { group, task, seconds, note } => {
+                self.group_task_mut(*group, *task)?.adjust(*seconds, note.clone(), at);
+            },
+            Op::Restore { state } => self.restore_state(state)?
+        }
+
+        self.event_seq = event.seq();
+
+        Ok(())
+    }
+
+    /// Replace the state with a serialized manager
+    fn restore_state(&mut self, state: &Value) -> Res<()> {
+        let mut doc = state.clone();
+        migrate::migrate(&mut doc)?;
+        let restored: Manager = serde_json::from_value(doc)?;
+
+        self.version = restored.version;
+        self.next_group = restored.next_group;
+        self.current_group = restored.current_group;
+        self.groups = restored.groups;
+        self.paused = restored.paused;
+
+        // Unique IDs are never given out twice, even after going back
+        self.next_uid = cmp::max(self.next_uid, restored.next_uid);
+        self.assign_uids();
 
         Ok(())
     }
 }
 
+/// HISTORY
+impl Manager {
+
+    /// Go back to the state before the last command.
+    /// Returns the undone action, and the groups it changed
+    pub fn undo(&mut self) -> Res<(String, Vec<Group>)> {
+        self.step(true)
+    }
+
+    /// Re-apply the last undone command.
+    /// Returns the redone action, and the groups it changed
+    pub fn redo(&mut self) -> Res<(String, Vec<Group>)> {
+        self.step(false)
+    }
+
+    fn step(&mut self, undo: bool) -> Res<(String, Vec<Group>)> {
+        let mut history = self.load_history()?;
+        let entry = history.pop(undo)
+            .ok_or_else(|| ResErr::from(if undo { "Nothing to undo" } else { "Nothing to redo" }))?;
+
+        // Keep where the log is now so this step can be reversed.
+        // Toggling the same archived groups again reverses that too
+        let reverse = Entry::new(entry.action().clone(), self.event_seq, entry.archive().cloned());
+        history.push_reverse(undo, reverse);
+
+        let changed = self.restore(&entry)?;
+
+        self.storage_mut()?.save_document(history::DOCUMENT, &serde_json::to_value(&history)?)?;
+        self.record_history = false;
+
+        Ok((entry.action().clone(), changed))
+    }
+
+    /// Replace the current state with the one the entry was at.
+    /// Returns the groups that differ from before
+    fn restore(&mut self, entry: &Entry) -> Res<Vec<Group>> {
+        let restored = self.replay_to(entry.seq())?;
+
+        let mut changed = Vec::new();
+        for group in &restored.groups {
+            let before = self.groups.iter().find(|g| g.id == group.id);
+            let differs = match before {
+                Some(before) => serde_json::to_value(before)? != serde_json::to_value(group)?,
+                None => true
+            };
+
+            if differs {
+                changed.push(group.clone());
+            }
+        }
+
+        self.emit(Op::Restore { state: serde_json::to_value(&restored)? })?;
+
+        if let Some(changes) = entry.archive() {
+            let mut doc = changes.clone();
+            migrate::migrate(&mut doc)?;
+
+            self.archive_mut()?.toggle(serde_json::from_value(doc)?);
+            self.archive_changed = true;
+        }
+
+        Ok(changed)
+    }
+
+    /// Rebuild the manager as it was right after the event with the seq
+    fn replay_to(&mut self, seq: u64) -> Res<Manager> {
+        let mut past = Manager::new();
+
+        for event in self.events()?.iter().take_while(|e| e.seq() <= seq) {
+            past.apply(event)?;
+        }
+
+        if past.event_seq != seq {
+            return Err(ResErr::from(format!("The event log does not reach event {}", seq)));
+        }
+
+        Ok(past)
+    }
+
+    /// Add the loaded state to the history, if this unit of work changed anything
+    fn record(&self, storage: &mut dyn Storage) -> Res<()> {
+        let loaded_seq = match self.loaded_seq {
+            Some(seq) => seq,
+            _ => return Ok(())
+        };
+
+        if self.event_seq == loaded_seq && !self.archive_changed {
+            return Ok(());
+        }
+
+        let archive = match (&self.archive_loaded, &self.archive) {
+            (Some(before), Some(after)) if self.archive_changed =>
+                Some(serde_json::to_value(before.difference(after))?),
+            _ => None
+        };
+        let action = self.action.clone().unwrap_or_else(|| String::from("change"));
+
+        let mut history = Self::read_history(storage)?;
+        history.record(Entry::new(action, loaded_seq, archive));
+
+        storage.save_document(history::DOCUMENT, &serde_json::to_value(&history)?)
+    }
+
+    fn load_history(&mut self) -> Res<History> {
+        Self::read_history(self.storage_mut()?)
+    }
+
+    fn read_history(storage: &mut dyn Storage) -> Res<History> {
+        match storage.load_document(history::DOCUMENT)? {
+            Some(doc) => Ok(serde_json::from_value(doc)?),
+            None => Ok(History::default())
+        }
+    }
+}
+
 /// PUBLIC
 impl Manager {
 
@@ -71,17 +545,14 @@ impl Manager {
     }
 
     pub fn use_group(&mut self, group_id: usize) -> Res<Group> {
-        let group = self.group_by_id(group_id)
-            .ok_or(ResErr::from("Could not find group!"))?;
-        let clone = group.clone();
-
-        self.current_group = Some(group.id);
+        let clone = self.group_mut(group_id)?.clone();
+        self.emit(Op::UseGroup { group: Some(group_id) })?;
 
         Ok(clone)
     }
 
-    pub fn reset_group(&mut self) {
-        self.current_group = None;
+    pub fn reset_group(&mut self) -> Res<()> {
+        self.emit(Op::UseGroup { group: None })
     }
 
     pub fn add_group(&mut self, name: String) -> Res<Group> {
@@ -93,313 +564,1755 @@ impl Manager {
             return Err(ResErr::from("Group already exists"));
         }
 
-        let new_group = Group::new(self.next_group, name);
-        let clone = new_group.clone();
+        self.emit(Op::AddGroup { name })?;
+
+        self.groups.last()
+            .cloned()
+            .ok_or_else(|| ResErr::from("Could not add group!"))
+    }
 
-        self.next_group += 1;
-        self.groups.push(new_group);
+    /// Rename a group. Names stay unique, and the default group keeps
+    /// today's date for as long as it is the default
+    pub fn rename_group(&mut self, group_id: usize, name: String) -> Res<Group> {
+        let old_name = self.group_mut(group_id)?.name.clone();
 
-        Ok(clone)
+        if old_name == default_group_name() && self.current_group.is_none() {
+            return Err(ResErr::from(
+                "Can't rename the default group, use another group first with track use"
+            ));
+        }
+
+        if self.group_by_name(&name).is_some() {
+            return Err(ResErr::from("Group already exists"));
+        }
+
+        self.emit(Op::RenameGroup { group: group_id, name })?;
+
+        Ok(self.group_mut(group_id)?.clone())
     }
 
-    pub fn extract_groups(
-        &mut self, retain: bool, group_ids: Vec<usize>
-    ) -> Res<Vec<Group>> {
+    /// Carry the unfinished tasks of the most recent earlier day's group into
+    /// the given day's group. Each carried task links back to the task it
+    /// continues. Copied tasks stay open in the earlier group, moved tasks
+    /// are closed off there, or removed if no time was tracked on them.
+    /// A moved task that is running is stopped, and runs on in the given day.
+    /// Returns the tasks carried over
+    pub fn carry_tasks(&mut self, into: usize, mode: CarryMode) -> Res<Vec<Task>> {
+        let day_of = |g: &Group| NaiveDate::parse_from_str(&g.name, DATE_FORMAT).ok();
+
+        let into_day = day_of(self.group_mut(into)?)
+            .ok_or_else(|| ResErr::from("Tasks can only be carried into a day's group"))?;
+
+        let from = self.groups.iter()
+            .filter_map(|g| day_of(g).filter(|day| *day < into_day).map(|day| (day, g.id)))
+            .max()
+            .map(|(_, id)| id);
+
+        let from = match from {
+            Some(from) => from,
+            _ => return Ok(Vec::new())
+        };
+
+        // Don't carry a task twice
+        let carried_before: Vec<usize> = self.group_mut(into)?.tasks.iter()
+            .filter_map(|t| t.origin.filter(|o| o.group == from).map(|o| o.task))
+            .collect();
+
+        let to_carry: Vec<usize> = self.group_mut(from)?.tasks.iter()
+            .filter(|t| !t.is_complete && !t.carried && !carried_before.contains(&t.id))
+            .map(|t| t.id)
+            .collect();
+
+        let mut carried = Vec::new();
+        for task in to_carry {
+            // A moved task that is running carries on running in the new group
+            let running = mode == CarryMode::Move && self.group_mut(from)?.current_task == Some(task);
+            let at = time::timestamp();
+            if running {
+                self.emit_at(Op::StopTask { group: from, task }, at)?;
+            }
 
-        let mut extracted_groups: Vec<Group> = Vec::new();
-        let mut extracted_ids: Vec<usize> = Vec::new();
+            self.emit(Op::CarryTask { group: from, task, into_group: into, mode })?;
 
-        for g in self.groups.iter() {
-            if (retain && !group_ids.contains(&g.id))
-                || (!retain && group_ids.contains(&g.id))  {
+            let added = self.group_mut(into)?.tasks.last()
+                .cloned()
+                .ok_or_else(|| ResErr::from("Could not carry task!"))?;
 
-                extracted_ids.push(g.id);
-                extracted_groups.push(g.clone());
+            if running {
+                self.emit_at(Op::StartTask { group: into, task: added.id, note: None, timebox: None }, at)?;
+                carried.push(self.task_clone(into, added.id)?);
+            } else {
+                carried.push(added);
             }
         }
+
+        Ok(carried)
+    }
+
+    pub fn extract_groups(
+        &mut self, retain: bool, group_ids: Vec<usize>
+    ) -> Res<Vec<Group>> {
+
+        let extracted_ids: Vec<usize> = self.groups.iter()
+            .filter(|g| (retain && !group_ids.contains(&g.id))
+                || (!retain && group_ids.contains(&g.id)))
+            .map(|g| g.id)
+            .collect();
         
         // Can't remove the current selected
         if extracted_ids.contains(&self.resolve_group()?.id) {
             return Err(ResErr::from("Cannot archive the current group"));
         }
 
-        // Only retain the ones that weren't extracted
-        self.groups.retain(|g| !extracted_ids.contains(&g.id));
-        
-        Ok(extracted_groups)
+        // Extracted groups shouldn't keep accumulating time
+        for id in &extracted_ids {
+            if let Some(task) = self.group_mut(*id)?.current_task {
+                self.emit(Op::StopTask { group: *id, task })?;
+            }
+        }
+
+        let extracted_groups: Vec<Group> = self.groups.iter()
+            .filter(|g| extracted_ids.contains(&g.id))
+            .cloned()
+            .collect();
+
+        self.emit(Op::ExtractGroups { groups: extracted_ids })?;
+        
+        Ok(extracted_groups)
+    }
+
+    /// Extract the groups (see extract_groups) and move them into the archive.
+    /// Returns the archived groups along with their archive IDs
+    pub fn archive_groups(
+        &mut self, retain: bool, group_ids: Vec<usize>
+    ) -> Res<Vec<(usize, Group)>> {
+
+        // Load the archive before changing anything, in case it can't be read
+        self.archive_mut()?;
+        let extracted = self.extract_groups(retain, group_ids)?;
+
+        let archive = self.archive_mut()?;
+        let archived = extracted.into_iter()
+            .map(|g| (archive.add(g.clone()), g))
+            .collect();
+
+        self.archive_changed = true;
+
+        Ok(archived)
+    }
+
+    /// Move a group out of the archive and back into the manager.
+    /// It is given a new group ID
+    pub fn unarchive(&mut self, archive_id: usize) -> Res<Group> {
+        let name = self.archive_mut()?
+            .get(archive_id)
+            .map(|g| g.group().name.clone())
+            .ok_or_else(|| ResErr::from("Could not find archived group!"))?;
+
+        if self.group_by_name(&name).is_some() {
+            return Err(ResErr::from(format!("A group named {} already exists", name)));
+        }
+
+        let mut group = self.archive_mut()?
+            .take(archive_id)
+            .ok_or_else(|| ResErr::from("Could not find archived group!"))?;
+        self.archive_changed = true;
+
+        group.id = self.next_group;
+        self.emit(Op::InsertGroup { group: group.clone() })?;
+
+        Ok(group)
+    }
+
+    /// Get the archived groups
+    pub fn archive(&mut self) -> Res<&Archive> {
+        Ok(self.archive_mut()?)
+    }
+
+    pub fn minimize_ids(&mut self) -> Res<()> {
+        self.emit(Op::MinimizeIds)
+    }
+
+    // GROUP DELEGATES
+
+    pub fn add_task(&mut self, task_name: String) -> Res<Task> {
+        let group = self.resolve_group()?.id;
+        self.emit(Op::AddTask { group, name: task_name })?;
+
+        self.group_mut(group)?.tasks.last()
+            .cloned()
+            .ok_or_else(|| ResErr::from("Could not add task!"))
+    }
+
+    pub fn remove_task(&mut self, task: TaskRef) -> Res<Task> {
+        let TaskRef { group, task: task_id } = task;
+        let clone = self.task_clone(group, task_id)?;
+
+        self.emit(Op::RemoveTask { group, task: task_id })?;
+
+        Ok(clone)
+    }
+
+    pub fn rename_task(&mut self, task: TaskRef, name: String) -> Res<Task> {
+        let TaskRef { group, task: task_id } = task;
+        self.task_clone(group, task_id)?;
+
+        self.emit(Op::RenameTask { group, task: task_id, name })?;
+
+        self.task_clone(group, task_id)
+    }
+
+    /// Move a task into another group, where it gets a new ID.
+    /// It keeps its sessions, and keeps running if it was
+    pub fn move_task(&mut self, task: TaskRef, into: usize) -> Res<Task> {
+        let TaskRef { group, task: task_id } = task;
+        let clone = self.task_clone(group, task_id)?;
+        let into_group = self.group_mut(into)?.clone();
+
+        if group == into {
+            return Err(ResErr::from(format!("{} is already in {}", clone.name, into_group.name)));
+        }
+
+        // A group only has one running task
+        if let Some(running) = into_group.current_task.filter(|_| clone.current_session().is_some()) {
+            let running = self.task_clone(into, running)?;
+            return Err(ResErr::from(format!(
+                "{} is running in {}, stop it before moving {} there", running.name, into_group.name, clone.name
+            )));
+        }
+
+        self.emit(Op::MoveTask { group, task: task_id, into_group: into })?;
+
+        self.group_mut(into)?.tasks.last()
+            .cloned()
+            .ok_or_else(|| ResErr::from("Could not move task!"))
+    }
+
+    /// Copy a task into another group, where it gets a new ID.
+    /// The copy keeps the sessions that have ended, but doesn't run
+    pub fn copy_task(&mut self, task: TaskRef, into: usize) -> Res<Task> {
+        let TaskRef { group, task: task_id } = task;
+        self.task_clone(group, task_id)?;
+        self.group_mut(into)?;
+
+        self.emit(Op::CopyTask { group, task: task_id, into_group: into })?;
+
+        self.group_mut(into)?.tasks.last()
+            .cloned()
+            .ok_or_else(|| ResErr::from("Could not copy task!"))
+    }
+
+    /// Start the task at the given time, or now, optionally timeboxed.
+    /// Only one task runs at a time, so the task running in any group is
+    /// stopped at that time. In parallel, only the task running in the
+    /// same group is stopped.
+    /// Returns the started task, and the tasks that were stopped
+    pub fn start_task(
+        &mut self, task: TaskRef, note: Option<String>, timebox: Option<Timebox>, at: Option<i64>, parallel: bool
+    ) -> Res<(Task, Vec<Task>)> {
+
+        self.start_in(task.group, task.task, note, timebox, at, parallel)
+    }
+
+    /// Stop the running task, and take a break until it is resumed
+    pub fn pause(&mut self, at: Option<i64>) -> Res<Task> {
+        let at = Self::resolve_at(at)?;
+        let TaskRef { group, task } = self.running_task()?
+            .ok_or_else(|| ResErr::from("No task is running"))?;
+
+        self.task_clone(group, task)?.check_stop(at)?;
+        self.emit_at(Op::PauseTask { group, task }, at)?;
+
+        let paused = self.task_clone(group, task)?;
+        self.split_across_days(group, task)?;
+
+        Ok(paused)
+    }
+
+    /// Start the paused task again, ending the break.
+    /// Returns the resumed task, the length of the break, and any
+    /// tasks that were stopped
+    pub fn resume(&mut self, at: Option<i64>, parallel: bool) -> Res<(Task, i64, Vec<Task>)> {
+        let paused = self.paused
+            .ok_or_else(|| ResErr::from("No task is paused"))?;
+
+        let (task, stopped) = self.start_in(paused.group, paused.task, None, None, at, parallel)?;
+        let length = task.pauses.last()
+            .map(|p| p.duration())
+            .unwrap_or(0);
+
+        Ok((task, length, stopped))
+    }
+
+    /// Start a task in the given group, see start_task
+    fn start_in(
+        &mut self,
+        group: usize,
+        task_id: usize,
+        note: Option<String>,
+        timebox: Option<Timebox>,
+        at: Option<i64>,
+        parallel: bool
+    ) -> Res<(Task, Vec<Task>)> {
+
+        let at = Self::resolve_at(at)?;
+
+        let current = self.group_mut(group)?.current_task;
+        if current == Some(task_id) {
+            let task = self.task_clone(group, task_id)?;
+            if let Some(session) = task.current_session() {
+                return Err(ResErr::from(format!(
+                    "{} is already running, since {}", task.name, time::format_local(session.start)
+                )));
+            }
+        }
+        if let Some(curr) = current {
+            self.task_clone(group, curr)?.check_stop(at)?;
+        }
+        self.task_clone(group, task_id)?.check_start(at)?;
+
+        let running: Vec<(usize, usize)> = self.groups.iter()
+            .filter(|g| !parallel && g.id != group)
+            .filter_map(|g| g.current_task.map(|t| (g.id, t)))
+            .collect();
+
+        let mut stopped = Vec::new();
+        for (other_group, other_task) in running {
+            self.task_clone(other_group, other_task)?.check_stop(at)?;
+            self.emit_at(Op::StopTask { group: other_group, task: other_task }, at)?;
+
+            stopped.push(self.task_clone(other_group, other_task)?);
+            self.split_across_days(other_group, other_task)?;
+        }
+
+        self.emit_at(Op::StartTask { group, task: task_id, note, timebox }, at)?;
+
+        if let Some(curr) = current {
+            stopped.push(self.task_clone(group, curr)?);
+            self.split_across_days(group, curr)?;
+        }
+
+        // A backdated start can already cross into the next day
+        self.split_across_days(group, task_id)?;
+        let started = self.follow_split(TaskRef { group, task: task_id })?;
+
+        Ok((self.task_clone(started.group, started.task)?, stopped))
+    }
+
+    /// Stop the running task, in whichever group it is, at the given time, or now
+    pub fn stop_current(&mut self, at: Option<i64>) -> Res<Task> {
+        let at = Self::resolve_at(at)?;
+        let TaskRef { group: group_id, task: task_id } = self.running_task()?
+            .ok_or_else(|| ResErr::from("No task is running"))?;
+
+        self.task_clone(group_id, task_id)?.check_stop(at)?;
+        self.emit_at(Op::StopTask { group: group_id, task: task_id }, at)?;
+
+        let stopped = self.task_clone(group_id, task_id)?;
+        self.split_across_days(group_id, task_id)?;
+
+        Ok(stopped)
+    }
+
+    /// Complete the task, or the running task, at the given time, or now
+    pub fn complete_task(&mut self, task: Option<TaskRef>, at: Option<i64>) -> Res<Task> {
+        let at = Self::resolve_at(at)?;
+        let running = self.running_task()?;
+        let TaskRef { group: group_id, task: id } = match task {
+            Some(task) => self.follow_split(task)?,
+            _ => running.ok_or_else(|| ResErr::from("No task or current task!"))?
+        };
+
+        self.task_clone(group_id, id)?.check_stop(at)?;
+        self.emit_at(Op::CompleteTask { group: group_id, task: id }, at)?;
+
+        let completed = self.task_clone(group_id, id)?;
+        self.split_across_days(group_id, id)?;
+
+        Ok(completed)
+    }
+
+    /// Add a session that was worked without a timer running.
+    /// It starts at the given time, or ends now if there is none
+    pub fn log_time(
+        &mut self, task: TaskRef, seconds: i64, at: Option<i64>, note: Option<String>
+    ) -> Res<Task> {
+
+        if seconds <= 0 {
+            return Err(ResErr::from("Logged time must be more than zero"));
+        }
+
+        let too_long = || ResErr::from(format!("Can't log {}", time::duration_str(seconds)));
+        let start = match at {
+            Some(at) => at,
+            _ => time::timestamp().checked_sub(seconds)
+                .and_then(time::checked_timestamp)
+                .ok_or_else(too_long)?
+        };
+        let end = start.checked_add(seconds).ok_or_else(too_long)?;
+        Self::resolve_at(Some(end))?;
+
+        let TaskRef { group, task: task_id } = task;
+        self.task_clone(group, task_id)?.check_free(start, end, None)?;
+
+        self.emit(Op::LogSession { group, task: task_id, start, end, note })?;
+
+        self.task_clone(group, task_id)
+    }
+
+    /// Correct the time tracked on a task by a number of seconds, which
+    /// may be negative. The total can't be brought below zero
+    pub fn adjust_time(
+        &mut self, task: TaskRef, seconds: i64, note: Option<String>
+    ) -> Res<Task> {
+
+        let TaskRef { group, task: task_id } = task;
+        let task = self.task_clone(group, task_id)?;
+
+        if seconds == 0 {
+            return Err(ResErr::from("Adjustment must not be zero"));
+        }
+
+        if task.tracked() + seconds < 0 {
+            return Err(ResErr::from(format!(
+                "Cannot adjust {} below zero, it has {} tracked",
+                task.name, time::duration_str(task.tracked())
+            )));
+        }
+
+        self.emit(Op::AdjustTask { group, task: task_id, seconds, note })?;
+
+        self.task_clone(group, task_id)
+    }
+
+    /// The running tasks that have been idle for too long
+    pub fn idle_tasks(&self, limits: IdleLimits) -> Vec<IdleTask> {
+        let now = time::timestamp();
+
+        self.groups.iter()
+            .filter_map(|g| g.current_task.and_then(|id| g.task(id)).map(|t| (g, t)))
+            .filter_map(|(g, t)| t.current_session()
+                .and_then(|s| limits.idle_since(s.start, now))
+                .map(|since| IdleTask::new(g.id, g.name.clone(), t.clone(), since)))
+            .collect()
+    }
+
+    /// Stop a running task at an earlier time, cutting off the idle part
+    pub fn cut(&mut self, group_id: usize, task_id: usize, at: i64) -> Res<Task> {
+        let at = Self::resolve_at(Some(at))?;
+
+        self.task_clone(group_id, task_id)?.check_stop(at)?;
+        self.emit_at(Op::StopTask { group: group_id, task: task_id }, at)?;
+
+        let cut = self.task_clone(group_id, task_id)?;
+        self.split_across_days(group_id, task_id)?;
+
+        Ok(cut)
+    }
+
+    /// Get the group a task is in, by the task's unique ID
+    pub fn group_of(&self, task: &Task) -> Option<&Group> {
+        self.groups.iter().find(|g| g.tasks.iter().any(|t| t.uid == task.uid))
+    }
+
+    /// Get a group by its ID
+    pub fn group_with_id(&mut self, group_id: usize) -> Res<&Group> {
+        Ok(self.group_mut(group_id)?)
+    }
+
+    /// Replace the tasks of a group with ones edited by hand.
+    /// New tasks are given the next task IDs, and the running task
+    /// becomes the current task. A task that starts running in the
+    /// form stops the tasks running in other groups, like start_task,
+    /// which are returned with the group
+    pub fn replace_tasks(&mut self, group_id: usize, form: GroupForm) -> Res<(Group, Vec<Task>)> {
+        let mut group = self.group_mut(group_id)?.clone();
+        let was_running = group.current_task
+            .and_then(|id| group.task(id))
+            .and_then(|t| t.current_session().map(|s| (t.id, s.start)));
+
+        let paused = self.paused.filter(|p| p.group == group_id).map(|p| p.task);
+        if let Some(task) = form.tasks.iter().find(|t| t.id != paused && t.pauses.iter().any(|p| p.end.is_none())) {
+            return Err(ResErr::from(format!(
+                "Only the paused task can have a break still going, not {}", task.name
+            )));
+        }
+
+        let max_id = form.tasks.iter().filter_map(|t| t.id).max().unwrap_or(0);
+        let mut next_task = cmp::max(group.next_task, max_id + 1);
+
+        let tasks: Vec<Task> = form.tasks.into_iter()
+            .map(|t| {
+                // Unique IDs and links between days aren't edited, existing tasks keep theirs
+                let existing = t.id.and_then(|id| group.task(id));
+
+                Task {
+                    id: t.id.unwrap_or_else(|| {
+                        next_task += 1;
+                        next_task - 1
+                    }),
+                    uid: existing.map(|e| e.uid).unwrap_or(0),
+                    name: t.name,
+                    sessions: t.sessions.into_iter()
+                        .map(|s| Session { start: s.start, end: s.end, note: s.note, kind: s.kind, timebox: s.timebox })
+                        .collect(),
+                    adjustments: t.adjustments.into_iter()
+                        .map(|a| Adjustment { at: a.at, seconds: a.seconds, note: a.note })
+                        .collect(),
+                    pauses: t.pauses.into_iter()
+                        .map(|p| Pause { start: p.start, end: p.end })
+                        .collect(),
+                    is_complete: t.is_complete,
+                    origin: existing.and_then(|e| e.origin),
+                    carried: existing.map(|e| e.carried).unwrap_or(false)
+                }
+            })
+            .collect();
+
+        let running = tasks.iter()
+            .find_map(|t| t.current_session().map(|s| (t.id, s.start)));
+
+        group.current_task = running.map(|(id, _)| id);
+        group.next_task = next_task;
+        group.tasks = tasks;
+
+        self.emit(Op::ReplaceGroup { group })?;
+
+        // Tasks already running in parallel stay that way
+        let mut stopped = Vec::new();
+        if let Some((_, at)) = running.filter(|r| Some(*r) != was_running) {
+            let others: Vec<(usize, usize)> = self.groups.iter()
+                .filter(|g| g.id != group_id)
+                .filter_map(|g| g.current_task.map(|t| (g.id, t)))
+                .collect();
+
+            for (other_group, other_task) in others {
+                self.task_clone(other_group, other_task)?.check_stop(at)?;
+                self.emit_at(Op::StopTask { group: other_group, task: other_task }, at)?;
+
+                stopped.push(self.task_clone(other_group, other_task)?);
+                self.split_across_days(other_group, other_task)?;
+            }
+        }
+
+        Ok((self.group_mut(group_id)?.clone(), stopped))
+    }
+
+    /// Get a copy of a task
+    pub fn task(&mut self, task: TaskRef) -> Res<Task> {
+        self.task_clone(task.group, task.task)
+    }
+
+    /// Find a task from how it is referred to: by its unique ID after
+    /// UID_PREFIX, in any group, or in the current group by its ID or
+    /// its name (see resolve::find)
+    pub fn find_task(&mut self, value: &str) -> Res<TaskRef> {
+        self.find_task_matching(value, Matching::Fuzzy)
+    }
+
+    /// Find a task like find_task, but without fuzzy name matches.
+    /// For commands that remove data
+    pub fn find_task_strict(&mut self, value: &str) -> Res<TaskRef> {
+        self.find_task_matching(value, Matching::Strict)
+    }
+
+    fn find_task_matching(&mut self, value: &str, matching: Matching) -> Res<TaskRef> {
+        if let Some(uid) = value.strip_prefix(UID_PREFIX) {
+            return self.find_task_uid(uid.parse::<u64>()?);
+        }
+
+        // A number is only ever an ID, never part of a name
+        let group = self.resolve_group()?;
+        if let Ok(id) = value.trim().parse::<usize>() {
+            return group.task(id)
+                .map(|t| TaskRef { group: group.id, task: t.id })
+                .ok_or_else(|| ResErr::from(format!("There is no task {} in {}", id, group.name)));
+        }
+
+        let candidates: Vec<(usize, &str)> = group.tasks.iter()
+            .map(|t| (t.id, t.name.as_str()))
+            .collect();
+
+        match resolve::find(value, &candidates, matching) {
+            Found::One(task) => Ok(TaskRef { group: group.id, task }),
+            Found::Ambiguous(ids) => Err(ambiguous("task", value, ids.iter()
+                .filter_map(|id| group.task(*id))
+                .map(|t| format!("{} : {}", t.id, t.name))
+                .collect())),
+            Found::None => Err(ResErr::from(format!("No task in {} matches {}", group.name, value)))
+        }
+    }
+
+    /// Find a group from how it is referred to: by its unique ID after
+    /// UID_PREFIX, its ID, a day such as "yesterday", or its name
+    /// (see resolve::find)
+    pub fn find_group(&mut self, value: &str) -> Res<usize> {
+        self.find_group_matching(value, Matching::Fuzzy)
+    }
+
+    /// Find a group like find_group, but without fuzzy name matches.
+    /// For commands that remove data
+    pub fn find_group_strict(&mut self, value: &str) -> Res<usize> {
+        self.find_group_matching(value, Matching::Strict)
+    }
+
+    fn find_group_matching(&mut self, value: &str, matching: Matching) -> Res<usize> {
+        if let Some(uid) = value.strip_prefix(UID_PREFIX) {
+            return self.find_group_uid(uid.parse::<u64>()?);
+        }
+
+        // A number is only ever an ID, never part of a name
+        if let Ok(id) = value.trim().parse::<usize>() {
+            return self.group_by_id(id)
+                .map(|g| g.id)
+                .ok_or_else(|| ResErr::from(format!("There is no group {}", id)));
+        }
+
+        if let Some(day) = time::parse_day(value) {
+            let name = day.format(DATE_FORMAT).to_string();
+
+            return self.group_by_name(&name)
+                .map(|g| g.id)
+                .ok_or_else(|| ResErr::from(format!("There is no group for {}", name)));
+        }
+
+        let candidates: Vec<(usize, &str)> = self.groups.iter()
+            .map(|g| (g.id, g.name.as_str()))
+            .collect();
+
+        match resolve::find(value, &candidates, matching) {
+            Found::One(group) => Ok(group),
+            Found::Ambiguous(ids) => Err(ambiguous("group", value, self.groups.iter()
+                .filter(|g| ids.contains(&g.id))
+                .map(|g| format!("{} : {}", g.id, g.name))
+                .collect())),
+            Found::None => Err(ResErr::from(format!("No group matches {}", value)))
+        }
+    }
+
+    /// Time tracked on a task, added to the time of every task it
+    /// was carried over from. Returns the total, and how many tasks it covers
+    pub fn tracked_across_days(&mut self, task: TaskRef) -> Res<(i64, usize)> {
+        let mut task = self.task_clone(task.group, task.task)?;
+
+        let mut total = task.tracked();
+        let mut count = 1;
+        let limit = self.groups.len();
+
+        // Archived or removed tasks end the chain
+        while let Some(origin) = task.origin.filter(|_| count <= limit) {
+            task = match self.group_by_id(origin.group).and_then(|g| g.task(origin.task)) {
+                Some(earlier) => earlier.clone(),
+                _ => break
+            };
+
+            total += task.tracked();
+            count += 1;
+        }
+
+        Ok((total, count))
+    }
+
+    /// Move the start and/or end of a session, numbered as in the sessions list.
+    /// The session must still not overlap the other sessions of the task
+    pub fn edit_session(
+        &mut self, task: TaskRef, number: usize, start: Option<i64>, end: Option<i64>
+    ) -> Res<Task> {
+
+        let TaskRef { group, task: task_id } = task;
+        let task = self.task_clone(group, task_id)?;
+        let index = task.session_index(number)?;
+        let session = &task.sessions[index];
+
+        if session.is_running() && end.is_some() {
+            return Err(ResErr::from(format!(
+                "Session {} is still running, stop {} to end it", number, task.name
+            )));
+        }
+
+        let start = Self::resolve_at(Some(start.unwrap_or(session.start)))?;
+        let end = end.or(session.end).map(|e| Self::resolve_at(Some(e))).transpose()?;
+
+        if end.map(|e| e <= start).unwrap_or(false) {
+            return Err(ResErr::from("A session must end after it starts"));
+        }
+
+        task.check_free(start, end.unwrap_or_else(time::timestamp), Some(index))?;
+
+        self.emit(Op::EditSession { group, task: task_id, index, start, end })?;
+
+        self.task_clone(group, task_id)
+    }
+
+    /// Delete a session, numbered as in the sessions list.
+    /// Deleting the running session stops the task
+    pub fn remove_session(&mut self, task: TaskRef, number: usize) -> Res<Task> {
+        let TaskRef { group, task: task_id } = task;
+        let index = self.task_clone(group, task_id)?.session_index(number)?;
+
+        self.emit(Op::RemoveSession { group, task: task_id, index })?;
+
+        self.task_clone(group, task_id)
+    }
+}
+
+/// PRIVATE
+impl Manager {
+    /// Create a base manager. 
+    /// This will likely only be called once, and then is on file creation
+    fn new() -> Self {
+        Manager {
+            version: migrate::SCHEMA_VERSION,
+            event_seq: 0,
+            next_group: 1,
+            next_uid: 1,
+            current_group: None,
+            groups: Vec::new(),
+            paused: None,
+            storage: None,
+            pending: Vec::new(),
+            snapshot_seq: None,
+            archive: None,
+            archive_changed: false,
+            loaded_seq: None,
+            archive_loaded: None,
+            action: None,
+            record_history: false,
+            split_days: false,
+            new_day: None
+        }
+    }
+
+    /// End the break of the paused task, if there is one
+    fn end_break(&mut self, at: i64) -> Res<()> {
+        if let Some(paused) = self.paused.take() {
+            let task = self.group_task_mut(paused.group, paused.task)?;
+
+            // Work started earlier elsewhere can't end the break before it began
+            if let Some(pause) = task.pauses.iter_mut().find(|p| p.end.is_none()) {
+                pause.end = Some(cmp::max(at, pause.start));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Split the sessions of the task that cross the day start, if enabled.
+    /// The part after it goes to a task of the same name in
+    /// the next day's group, both of which are created if needed.
+    /// Only groups named by a date are split, other groups aren't tied to a day
+    fn split_across_days(&mut self, group_id: usize, task_id: usize) -> Res<()> {
+        if !self.split_days {
+            return Ok(());
+        }
+
+        let now = time::timestamp();
+        let mut to_split = vec![(group_id, task_id)];
+
+        while let Some((group, task)) = to_split.pop() {
+            let group_name = self.group_mut(group)?.name.clone();
+            if NaiveDate::parse_from_str(&group_name, DATE_FORMAT).is_err() {
+                continue;
+            }
+
+            let task_clone = self.task_clone(group, task)?;
+
+            let day_of = |stamp: i64| time::day_of(stamp).format(DATE_FORMAT).to_string();
+            let boundary = task_clone.sessions.iter()
+                .map(|s| (time::next_day_start(s.start), s.end.unwrap_or(now)))
+                .find(|(boundary, end)| boundary < end && day_of(*boundary) != group_name)
+                .map(|(boundary, _)| boundary);
+
+            let at = match boundary {
+                Some(at) => at,
+                _ => continue
+            };
+
+            let day = day_of(at);
+            let into_group = match self.group_by_name(&day) {
+                Some(g) => g.id,
+                _ => self.add_group(day)?.id
+            };
+
+            let existing = self.group_mut(into_group)?.tasks.iter()
+                .find(|t| t.name == task_clone.name)
+                .map(|t| t.id);
+            let into_task = match existing {
+                Some(id) => id,
+                _ => {
+                    self.emit(Op::AddTask { group: into_group, name: task_clone.name.clone() })?;
+                    self.group_mut(into_group)?.next_task - 1
+                }
+            };
+
+            self.emit(Op::SplitSession { group, task, boundary: at, into_group, into_task })?;
+
+            // Check both again, for sessions spanning several days
+            to_split.push((group, task));
+            to_split.push((into_group, into_task));
+        }
+
+        Ok(())
+    }
+
+    /// Where the task's last session is running now. A session split across
+    /// days continues in a task of the same name, from the time the part
+    /// before ends. Returns the task itself if its session wasn't split
+    fn follow_split(&mut self, task: TaskRef) -> Res<TaskRef> {
+        let origin = self.task_clone(task.group, task.task)?;
+        let mut at = match origin.sessions.last().and_then(|s| s.end) {
+            Some(end) => end,
+            _ => return Ok(task)
+        };
+
+        loop {
+            let next = self.groups.iter()
+                .flat_map(|g| g.tasks.iter().map(move |t| (g.id, t)))
+                .filter(|(_, t)| t.name == origin.name)
+                .find_map(|(group, t)| t.sessions.iter()
+                    .find(|s| s.start == at && s.end.map(|end| end > at).unwrap_or(true))
+                    .map(|s| (TaskRef { group, task: t.id }, s.end)));
+
+            match next {
+                Some((continued, None)) => return Ok(continued),
+                Some((_, Some(end))) => at = end,
+                _ => return Ok(task)
+            }
+        }
+    }
+
+    /// Move the part of a session after the given time into another task.
+    /// A running session keeps running in the other task
+    fn split_session(
+        &mut self, group_id: usize, task_id: usize, at: i64, into_group: usize, into_task: usize
+    ) -> Res<()> {
+
+        let session = self.group_task_mut(group_id, task_id)?
+            .sessions
+            .iter_mut()
+            .find(|s| s.start < at && s.end.map(|e| e > at).unwrap_or(true))
+            .ok_or_else(|| ResErr::from("Could not find session to split!"))?;
+
+        let mut rest = session.clone();
+        rest.start = at;
+        session.end = Some(at);
+
+        let is_running = rest.is_running();
+        if is_running {
+            let group = self.group_mut(group_id)?;
+            if group.current_task == Some(task_id) {
+                group.current_task = None;
+            }
+        }
+
+        let task = self.group_task_mut(into_group, into_task)?;
+        task.insert_session(rest);
+
+        if is_running {
+            task.is_complete = false;
+            self.group_mut(into_group)?.current_task = Some(into_task);
+        }
+
+        Ok(())
+    }
+
+    /// Add a task to a group, continuing a task of another group
+    fn carry_task(&mut self, group_id: usize, task_id: usize, into_group: usize, mode: CarryMode) -> Res<()> {
+        let origin = self.task_clone(group_id, task_id)?;
+
+        // A moved task that has no time tracked isn't worth keeping,
+        // so the task carried over continues from where it came from
+        let remove = mode == CarryMode::Move && origin.sessions.is_empty() && origin.adjustments.is_empty();
+
+        let uid = self.take_uid();
+        let into = self.group_mut(into_group)?;
+        into.add_task(uid, origin.name.clone());
+
+        let task = into.tasks.last_mut()
+            .ok_or_else(|| ResErr::from("Could not carry task!"))?;
+        task.origin = if remove { origin.origin } else { Some(TaskRef { group: group_id, task: task_id }) };
+
+        if remove {
+            self.group_mut(group_id)?.remove_task(task_id)?;
+        } else if mode == CarryMode::Move && origin.current_session().is_none() {
+            self.group_task_mut(group_id, task_id)?.carried = true;
+        }
+
+        Ok(())
+    }
+
+    /// Add a task to another group under a new ID, either moving it
+    /// there or adding a copy with its own unique ID
+    fn transfer_task(&mut self, group_id: usize, task_id: usize, into_group: usize, is_move: bool) -> Res<()> {
+        let mut task = self.task_clone(group_id, task_id)?;
+        let is_running = task.current_session().is_some();
+
+        if is_move {
+            self.group_mut(group_id)?.remove_task(task_id)?;
+        } else {
+            // The copy is not running, or on a break
+            task.uid = self.take_uid();
+            task.sessions.retain(|s| !s.is_running());
+            task.pauses.retain(|p| p.end.is_some());
+            task.carried = false;
+        }
+
+        let into = self.group_mut(into_group)?;
+        task.id = into.next_task;
+        into.next_task += 1;
+
+        if is_move && is_running {
+            into.current_task = Some(task.id);
+        }
+
+        let moved = TaskRef { group: into_group, task: task.id };
+        into.tasks.push(task);
+
+        if is_move {
+            if self.paused == Some(Paused { group: group_id, task: task_id }) {
+                self.paused = Some(Paused { group: into_group, task: moved.task });
+            }
+
+            // Tasks carried over from the moved task still refer to it
+            let from = Some(TaskRef { group: group_id, task: task_id });
+            for t in self.groups.iter_mut().flat_map(|g| g.tasks.iter_mut()).filter(|t| t.origin == from) {
+                t.origin = Some(moved);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The running task, in any group. Prefers the task running in
+    /// the current group, in case several are running in parallel
+    fn running_task(&mut self) -> Res<Option<TaskRef>> {
+        let current = self.resolve_group()?.id;
+        let mut running: Vec<TaskRef> = self.groups.iter()
+            .filter_map(|g| g.current_task.map(|task| TaskRef { group: g.id, task }))
+            .collect();
+        running.sort_by_key(|t| t.group != current);
+
+        Ok(running.first().cloned())
+    }
+
+    /// The time a change happened, defaulting to now.
+    /// Changes can be backdated, but not made in the future
+    fn resolve_at(at: Option<i64>) -> Res<i64> {
+        let now = time::timestamp();
+
+        match at {
+            Some(at) if at > now => Err(ResErr::from("Cannot use a time in the future")),
+            Some(at) => Ok(at),
+            _ => Ok(now)
+        }
+    }
+
+    /// Give out the next unique ID
+    fn take_uid(&mut self) -> u64 {
+        self.next_uid += 1;
+        self.next_uid - 1
+    }
+
+    /// Give a unique ID to every group and task that doesn't have one yet
+    fn assign_uids(&mut self) {
+        let mut next_uid = self.next_uid;
+        let mut assign = |uid: &mut u64| {
+            if *uid == 0 {
+                *uid = next_uid;
+                next_uid += 1;
+            }
+        };
+
+        for group in &mut self.groups {
+            assign(&mut group.uid);
+            group.tasks.iter_mut().for_each(|t| assign(&mut t.uid));
+        }
+
+        self.next_uid = next_uid;
+    }
+
+    fn find_task_uid(&mut self, uid: u64) -> Res<TaskRef> {
+        let found = self.groups.iter()
+            .find_map(|g| g.tasks.iter().find(|t| t.uid == uid).map(|t| TaskRef { group: g.id, task: t.id }));
+
+        if let Some(group) = self.groups.iter().find(|g| g.uid == uid) {
+            return Err(ResErr::from(format!("{}{} is the group {}, not a task", UID_PREFIX, uid, group.name)));
+        }
+
+        match found {
+            Some(task) => Ok(task),
+            _ => Err(self.not_found(uid)?)
+        }
+    }
+
+    fn find_group_uid(&mut self, uid: u64) -> Res<usize> {
+        let task = self.groups.iter()
+            .find_map(|g| g.tasks.iter().find(|t| t.uid == uid).map(|t| (g, t)));
+
+        if let Some((group, task)) = task {
+            return Err(ResErr::from(format!(
+                "{}{} is the task {} in {}, not a group", UID_PREFIX, uid, task.name, group.name
+            )));
+        }
+
+        match self.groups.iter().find(|g| g.uid == uid) {
+            Some(group) => Ok(group.id),
+            _ => Err(self.not_found(uid)?)
+        }
+    }
+
+    /// Explain why no group or task has the unique ID.
+    /// It may have been archived
+    fn not_found(&mut self, uid: u64) -> Res<ResErr> {
+        let archived = self.archive_mut()?.find_uid(uid)
+            .map(|g| (g.archive_id(), g.group().name().clone()));
+
+        Ok(match archived {
+            Some((archive_id, name)) => ResErr::from(format!(
+                "{}{} is archived in {} (archive ID {}), unarchive it first", UID_PREFIX, uid, name, archive_id
+            )),
+            _ => ResErr::from(format!("Nothing has the unique ID {}{}", UID_PREFIX, uid))
+        })
+    }
+
+    fn minimize(&mut self) {
+        // Should just be able to process in order
+        // We can assume the IDs always get larger as we go
+        // This assumes the groups/tasks are sorted by ID
+        
+        // Always start at 1 (the absolute min)
+        let mut next_min = 1;
+
+        // The old and new ID of each group
+        let mut remapped: Vec<(usize, usize)> = Vec::new();
+
+        for group in &mut self.groups {
+            // Get the ID we are processing
+            let process_id = group.id;
+
+            // Resolve the current here if necessary
+            let is_current = self.current_group
+                .map(|c| c == process_id)
+                .unwrap_or(false);
+
+            group.id = cmp::min(process_id, next_min);
+
+            if is_current {
+                self.current_group = Some(group.id);
+            }
+
+            if let Some(paused) = self.paused.as_mut().filter(|p| p.group == process_id) {
+                paused.group = group.id;
+            }
+
+            remapped.push((process_id, group.id));
+            next_min = group.id + 1;
+        }
+
+        self.next_group = next_min;
+
+        // Keep carried tasks linked to where they came from
+        let origins = self.groups.iter_mut()
+            .flat_map(|g| g.tasks.iter_mut())
+            .filter_map(|t| t.origin.as_mut());
+
+        for origin in origins {
+            if let Some((_, id)) = remapped.iter().find(|(old, _)| *old == origin.group) {
+                origin.group = *id;
+            }
+        }
+    }
+    
+    /// Get the archive, loading it from storage the first time
+    fn archive_mut(&mut self) -> Res<&mut Archive> {
+        if self.archive.is_none() {
+            let archive = self.storage_mut()?.load_archive()?;
+
+            self.archive_loaded = Some(archive.clone());
+            self.archive = Some(archive);
+        }
+
+        self.archive.as_mut()
+            .ok_or_else(|| ResErr::from("Could not load archive"))
+    }
+
+    fn storage_mut(&mut self) -> Res<&mut (dyn Storage + 'static)> {
+        self.storage.as_deref_mut()
+            .ok_or_else(|| ResErr::from("Manager has no storage"))
+    }
+
+    /// Get a mut group by its ID, failing if it doesn't exist
+    fn group_mut(&mut self, group_id: usize) -> Res<&mut Group> {
+        self.group_by_id(group_id)
+            .ok_or_else(|| ResErr::from("Could not find group!"))
+    }
+
+    /// Get a mut task by its group and ID, failing if it doesn't exist
+    fn group_task_mut(&mut self, group_id: usize, task_id: usize) -> Res<&mut Task> {
+        self.group_mut(group_id)?
+            .task_mut(task_id)
+            .ok_or_else(|| ResErr::from("Could not find task in group!"))
+    }
+
+    /// Get a copy of a task, failing if it doesn't exist
+    fn task_clone(&mut self, group_id: usize, task_id: usize) -> Res<Task> {
+        Ok(self.group_task_mut(group_id, task_id)?.clone())
+    }
+
+    /// Get a mut group by searching by ID
+    fn group_by_id(&mut self, group_id: usize) -> Option<&mut Group> {
+        self.groups.iter_mut().find(|group| group.id == group_id)
+    }
+
+    /// Get a mut group by searching by name
+    fn group_by_name(&mut self, group_name: &str) -> Option<&mut Group> {
+        self.groups.iter_mut().find(|group| group.name == group_name)
+    }
+
+    /// Resolve the current group.
+    /// This method assumes the default group already exists,
+    /// and it will NOT create it on the fly.
+    fn resolve_group(&mut self) -> Res<&mut Group> {
+        match self.current_group {
+            Some(curr) => {
+                // Find the current group
+                self.group_by_id(curr)
+                    .ok_or_else(|| ResErr::from("Could not resolve existing group!"))
+            },
+            _ => {
+                // Find the default group using the group_name()
+                let name = default_group_name();
+                self.group_by_name(&name)
+                    .ok_or_else(|| ResErr::from("Could not resolve default group!"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Group {
+    id: usize,
+
+    /// Unique across all groups and tasks, and never changes, unlike the ID.
+    /// 0 until one is given, for groups archived before there were unique IDs
+    #[serde(default)]
+    uid: u64,
+    next_task: usize,
+    current_task: Option<usize>,
+    name: String,
+    tasks: Vec<Task>
+}
+
+impl Group {
+    fn new(id: usize, uid: u64, name: String) -> Self {
+        Group {
+            id,
+            uid,
+            next_task: 1,
+            current_task: None,
+            name,
+            tasks: Vec::new()
+        }
+    }
+
+    fn add_task(&mut self, uid: u64, task_name: String) {
+        let task = Task::new(self.next_task, uid, task_name);
+
+        self.next_task += 1;
+        self.tasks.push(task);
+    }
+
+    fn remove_task(&mut self, task_id: usize) -> Res<()> {
+        if self.task_mut(task_id).is_none() {
+            return Err(ResErr::from("Could not find task in group"));
+        }
+
+        self.tasks.retain(|t| t.id != task_id);
+
+        if self.current_task == Some(task_id) {
+            self.current_task = None;
+        }
+
+        Ok(())
+    }
+
+    fn start_task(&mut self, task_id: usize, note: Option<String>, timebox: Option<Timebox>, at: i64) -> Res<()> {
+        if self.task_mut(task_id).is_none() {
+            return Err(ResErr::from("Could not find task in group!"));
+        }
+
+        // Stop current if there is a current
+        if let Some(curr) = self.current_task {
+            self.stop_task(curr, at)?;
+        }
+
+        let task = self.task_mut(task_id)
+            .ok_or_else(|| ResErr::from("Could not find task in group!"))?;
+
+        task.start(note, timebox, at);
+
+        // Set the current task
+        self.current_task = Some(task_id);
+
+        Ok(())
+    }
+
+    fn stop_task(&mut self, task_id: usize, at: i64) -> Res<()> {
+        let task = self.task_mut(task_id)
+            .ok_or_else(|| ResErr::from("Could not find task in group"))?;
+
+        // Stop the task
+        task.stop(at);
+
+        // Reset the current task
+        if self.current_task == Some(task_id) {
+            self.current_task = None;
+        }
+
+        Ok(())
+    }
+
+    fn complete_task(&mut self, task_id: usize, at: i64) -> Res<()> {
+        let task = self.task_mut(task_id)
+            .ok_or_else(|| ResErr::from("Could not find task in group!"))?;
+
+        task.complete(at);
+
+        // Completing stops the task, so it can no longer be current
+        if self.current_task == Some(task_id) {
+            self.current_task = None;
+        }
+    
+        Ok(())
+    }
+
+    fn remove_session(&mut self, task_id: usize, index: usize) -> Res<()> {
+        let task = self.task_mut(task_id)
+            .ok_or_else(|| ResErr::from("Could not find task in group!"))?;
+
+        if index >= task.sessions.len() {
+            return Err(ResErr::from("Could not find session!"));
+        }
+
+        let removed = task.sessions.remove(index);
+
+        // Without its running session the task is no longer current
+        if removed.is_running() && self.current_task == Some(task_id) {
+            self.current_task = None;
+        }
+
+        Ok(())
+    }
+
+    /// Get the task with id: task_id from this group
+    fn task(&self, task_id: usize) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.id == task_id)
+    }
+
+    /// Get the task with id: task_id as mutable from this group
+    fn task_mut(&mut self, task_id: usize) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|task| task.id == task_id)
+    }
+
+    /// Total time tracked across all tasks in the group, in seconds
+    pub fn tracked(&self) -> i64 {
+        self.tasks.iter().map(|t| t.tracked()).sum()
+    }
+
+    /// Number of pomodoros finished across all tasks in the group
+    pub fn pomodoros(&self) -> usize {
+        self.tasks.iter().map(|t| t.pomodoros()).sum()
+    }
+
+    fn has_pomodoros(&self) -> bool {
+        self.tasks.iter().any(|t| t.has_pomodoros())
+    }
+
+    fn has_breaks(&self) -> bool {
+        self.tasks.iter().any(|t| !t.pauses.is_empty())
+    }
+
+    // GETTERS
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn uid(&self) -> u64 {
+        self.uid
+    }
+
+    pub fn current_task(&self) -> Option<usize> {
+        self.current_task
+    }
+
+    pub fn tasks(&self) -> &Vec<Task> {
+        &self.tasks
+    }
+}
+
+/// Represents an individual task to complete.
+/// Holds the sessions worked on the task, which are
+/// used in computing time tracked for a task.
+/// Adjustments are kept apart from the sessions, so measured
+/// time can always be told apart from corrections
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Task {
+    id: usize,
+
+    /// See Group::uid
+    #[serde(default)]
+    uid: u64,
+    name: String,
+    sessions: Vec<Session>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    adjustments: Vec<Adjustment>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pauses: Vec<Pause>,
+    is_complete: bool,
+
+    /// The task this one was carried over from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    origin: Option<TaskRef>,
+
+    /// Moved into a later day's group, see Manager::carry_tasks
+    #[serde(default, skip_serializing_if = "is_false")]
+    carried: bool
+}
+
+impl Task {
+    fn new(id: usize, uid: u64, name: String) -> Self {
+        Task {
+            id,
+            uid,
+            name,
+            sessions: Vec::new(),
+            adjustments: Vec::new(),
+            pauses: Vec::new(),
+            is_complete: false,
+            origin: None,
+            carried: false
+        }
+    }
+
+    /// Add a finished session that was logged by hand
+    fn log(&mut self, start: i64, end: i64, note: Option<String>) {
+        let mut session = Session::new(start, note, None);
+        session.end = Some(end);
+        session.kind = SessionKind::Logged;
+
+        self.insert_session(session);
+    }
+
+    /// Add a session, keeping the sessions in order of when they started
+    fn insert_session(&mut self, session: Session) {
+        let index = self.sessions.iter()
+            .position(|s| s.start > session.start)
+            .unwrap_or(self.sessions.len());
+
+        self.sessions.insert(index, session);
+    }
+
+    /// Correct the time tracked by a number of seconds
+    fn adjust(&mut self, seconds: i64, note: Option<String>, at: i64) {
+        self.adjustments.push(Adjustment { at, seconds, note });
+    }
+
+    /// Move a session, keeping the sessions in order of when they started
+    fn edit_session(&mut self, index: usize, start: i64, end: Option<i64>) -> Res<()> {
+        let session = self.sessions.get_mut(index)
+            .ok_or_else(|| ResErr::from("Could not find session!"))?;
+
+        session.start = start;
+        session.end = end;
+
+        self.sessions.sort_by_key(|s| s.start);
+
+        Ok(())
+    }
+
+    /// Get the index of a session from its number in the sessions list
+    fn session_index(&self, number: usize) -> Res<usize> {
+        if number == 0 || number > self.sessions.len() {
+            return Err(ResErr::from(format!(
+                "{} has no session {}, see track sessions {}", self.name, number, self.id
+            )));
+        }
+
+        Ok(number - 1)
+    }
+
+    /// Check no session of the task overlaps the given span.
+    /// The session at skip, if any, is left out of the check
+    fn check_free(&self, start: i64, end: i64, skip: Option<usize>) -> Res<()> {
+        let now = time::timestamp();
+
+        let overlapping = self.sessions.iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != skip)
+            .map(|(_, s)| s)
+            .find(|s| start < s.end.unwrap_or(now) && s.start < end);
+
+        match overlapping {
+            Some(s) => Err(ResErr::from(format!(
+                "{} already has a session from {} to {}",
+                self.name,
+                time::format_local(s.start),
+                s.end.map(time::format_local).unwrap_or_else(|| String::from("now"))
+            ))),
+            _ => Ok(())
+        }
+    }
+
+    /// Open a new session starting at the given time, with the time it is meant to take.
+    /// If a session is already running, it is stopped first
+    fn start(&mut self, note: Option<String>, timebox: Option<Timebox>, at: i64) {
+        self.stop(at);
+        self.sessions.push(Session::new(at, note, timebox));
+
+        // Un-complete the task if it is started
+        self.is_complete = false;
+    }
+
+    /// Stop the task at the given time.
+    /// This will close the running session, if there is one
+    fn stop(&mut self, at: i64) {
+        if let Some(session) = self.sessions.iter_mut().find(|s| s.is_running()) {
+            session.end = Some(at);
+        }
+    }
+
+    /// Complete the task
+    /// Will the stop the current task, and mark as cimplete
+    fn complete(&mut self, at: i64) {
+        // Stop the task (it could be currently running)
+        self.stop(at);
+        self.is_complete = true;
+    }
+
+    /// Check the task can be stopped at the given time.
+    /// A session can't end before it started
+    fn check_stop(&self, at: i64) -> Res<()> {
+        match self.current_session() {
+            Some(session) if session.start > at => Err(ResErr::from(format!(
+                "Cannot stop {} at {}, before it started at {}",
+                self.name, time::format_local(at), time::format_local(session.start)
+            ))),
+            _ => Ok(())
+        }
     }
 
-    pub fn minimize_ids(&mut self) {
-        // Should just be able to process in order
-        // We can assume the IDs always get larger as we go
-        // This assumes the groups/tasks are sorted by ID
-        
-        // Always start at 1 (the absolute min)
-        let mut next_min = 1;
+    /// Check the task can be started at the given time.
+    /// A new session can't begin before the last one ended
+    fn check_start(&self, at: i64) -> Res<()> {
+        self.check_stop(at)?;
+
+        match self.sessions.iter().filter_map(|s| s.end).max() {
+            Some(end) if end > at => Err(ResErr::from(format!(
+                "Cannot start {} at {}, before its last session ended at {}",
+                self.name, time::format_local(at), time::format_local(end)
+            ))),
+            _ => Ok(())
+        }
+    }
 
-        for group in &mut self.groups {
-            // Get the ID we are processing
-            let process_id = group.id;
+    /// The session currently running, if any
+    pub fn current_session(&self) -> Option<&Session> {
+        self.sessions.iter().find(|s| s.is_running())
+    }
 
-            // Resolve the current here if necessary
-            let is_current = self.current_group
-                .map(|c| c == process_id)
-                .unwrap_or(false);
+    /// Total time tracked across all sessions and adjustments, in seconds.
+    /// A running session counts up until now
+    pub fn tracked(&self) -> i64 {
+        self.sessions.iter().map(|s| s.duration()).sum::<i64>() + self.adjusted()
+    }
 
-            group.id = cmp::min(process_id, next_min);
+    /// Total of the adjustments made to the task, in seconds
+    pub fn adjusted(&self) -> i64 {
+        self.adjustments.iter().map(|a| a.seconds).sum()
+    }
 
-            if is_current {
-                self.current_group = Some(group.id);
-            }
+    /// Number of pomodoros finished, i.e. pomodoro sessions that
+    /// ran for at least their timebox
+    pub fn pomodoros(&self) -> usize {
+        self.sessions.iter()
+            .filter(|s| !s.is_running())
+            .filter(|s| s.timebox.filter(|t| t.pomodoro).map(|t| s.duration() >= t.seconds).unwrap_or(false))
+            .count()
+    }
 
-            next_min = group.id + 1;
-        }
+    /// Whether any session of the task was started as a pomodoro
+    fn has_pomodoros(&self) -> bool {
+        self.sessions.iter().any(|s| s.timebox.map(|t| t.pomodoro).unwrap_or(false))
+    }
 
-        self.next_group = next_min;
+    /// Total time spent on breaks from the task, in seconds.
+    /// A break still going counts up until now
+    pub fn paused(&self) -> i64 {
+        self.pauses.iter().map(|p| p.duration()).sum()
     }
 
-    // GROUP DELEGATES
+    // GETTERS
 
-    pub fn add_task(&mut self, task_name: String) -> Res<Task> {
-        self.resolve_group()?.add_task(task_name)
+    pub fn id(&self) -> usize {
+        self.id
     }
 
-    pub fn remove_task(&mut self, task_id: usize) -> Res<Task> {
-        self.resolve_group()?.remove_task(task_id)
+    pub fn uid(&self) -> u64 {
+        self.uid
     }
 
-    pub fn start_task(&mut self, task_id: usize) -> Res<Task> {
-        self.resolve_group()?.start_task(task_id)      
+    pub fn name(&self) -> &String {
+        &self.name
     }
 
-    pub fn stop_current(&mut self) -> Res<Task> {
-        self.resolve_group()?.stop_current()
+    pub fn sessions(&self) -> &Vec<Session> {
+        &self.sessions
     }
 
-    pub fn complete_task(&mut self, task_id: Option<usize>) -> Res<Task> {
-        self.resolve_group()?.complete_task(task_id)
+    pub fn adjustments(&self) -> &Vec<Adjustment> {
+        &self.adjustments
     }
-}
 
-/// PRIVATE
-impl Manager {
-    /// Create a base manager. 
-    /// This will likely only be called once, and then is on file creation
-    fn new() -> Self {
-        Manager {
-            next_group: 1,
-            current_group: None,
-            groups: Vec::new()
-        }
+    pub fn pauses(&self) -> &Vec<Pause> {
+        &self.pauses
     }
-    
-    /// Get a mut group by searching by ID
-    fn group_by_id(&mut self, group_id: usize) -> Option<&mut Group> {
-        for group in &mut self.groups {
-            if group.id == group_id {
-                return Some(group);
-            }
-        }
 
-        None
+    pub fn is_complete(&self) -> bool {
+        self.is_complete
     }
 
-    /// Get a mut group by searching by name
-    fn group_by_name(&mut self, group_name: &String) -> Option<&mut Group> {
-        for group in &mut self.groups {
-            if group.name == *group_name {
-                return Some(group);
-            }
-        }
-
-        None
+    pub fn origin(&self) -> Option<TaskRef> {
+        self.origin
     }
 
-    /// Resolve the current group.
-    /// This method assumes the default group already exists,
-    /// and it will NOT create it on the fly.
-    fn resolve_group(&mut self) -> Res<&mut Group> {
-        match self.current_group {
-            Some(curr) => {
-                // Find the current group
-                return self.group_by_id(curr)
-                    .ok_or(ResErr::from("Could not resolve existing group!"))
-            },
-            _ => {
-                // Find the default group using the group_name()
-                let name = default_group_name();
-                return self.group_by_name(&name)
-                    .ok_or(ResErr::from("Could not resolve default group!"));
-            }
-        }
+    pub fn is_carried(&self) -> bool {
+        self.carried
     }
 }
 
+/// A single run of a task, from start to end.
+/// A session without an end is still running
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Group {
-    id: usize,
-    next_task: usize,
-    current_task: Option<usize>,
-    name: String,
-    tasks: Vec<Task>
+pub struct Session {
+    start: i64,
+    end: Option<i64>,
+    note: Option<String>,
+    #[serde(default, skip_serializing_if = "SessionKind::is_timed")]
+    kind: SessionKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timebox: Option<Timebox>
 }
 
-impl Group {
-    fn new(id: usize, name: String) -> Self {
-        Group {
-            id: id,
-            next_task: 1,
-            current_task: None,
-            name: name,
-            tasks: Vec::new()
-        }
+/// How long a session was meant to take, e.g. with track start --for
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Timebox {
+    seconds: i64,
+    /// Started with track pomodoro
+    #[serde(default)]
+    pomodoro: bool
+}
+
+impl Timebox {
+    pub fn new(seconds: i64, pomodoro: bool) -> Self {
+        Timebox { seconds, pomodoro }
     }
 
-    fn add_task(&mut self, task_name: String) -> Res<Task> {
-        let task = Task::new(self.next_task, task_name);
-        let clone = task.clone();
-        
-        self.next_task += 1;
-        self.tasks.push(task);
+    // GETTERS
 
-        Ok(clone)
+    pub fn seconds(&self) -> i64 {
+        self.seconds
     }
 
-    fn remove_task(&mut self, task_id: usize) -> Res<Task> {
-        let task = self.task_mut(task_id)
-            .ok_or(ResErr::from("Could not find task in group"))?;
-        let clone = task.clone();
+    pub fn is_pomodoro(&self) -> bool {
+        self.pomodoro
+    }
+}
 
-        self.tasks.retain(|t| *t != clone);
+/// How a session was recorded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    /// Measured by starting and stopping the task
+    #[default]
+    Timed,
 
-        if self.current_task.filter(|curr| *curr == clone.id).is_some() {
-            self.current_task = None;
+    /// Entered by hand with track log
+    Logged
+}
+
+impl SessionKind {
+    fn is_timed(&self) -> bool {
+        *self == SessionKind::Timed
+    }
+}
+
+impl Session {
+    fn new(start: i64, note: Option<String>, timebox: Option<Timebox>) -> Self {
+        Session {
+            start,
+            end: None,
+            note,
+            kind: SessionKind::Timed,
+            timebox
         }
+    }
 
-        Ok(clone)
+    pub fn is_running(&self) -> bool {
+        self.end.is_none()
     }
 
-    fn start_task(&mut self, task_id: usize) -> Res<Task> {
-        let task = self.task_mut(task_id)
-            .ok_or(ResErr::from("Could not find task in group!"))?;
-        let clone = task.clone();
+    /// Length of the session in seconds.
+    /// A running session is measured up until now
+    pub fn duration(&self) -> i64 {
+        self.end.unwrap_or_else(time::timestamp) - self.start
+    }
 
-        task.start();
+    /// How far the session ran past its timebox, in seconds.
+    /// Negative if it ended early
+    pub fn overrun(&self) -> Option<i64> {
+        self.timebox.map(|t| self.duration() - t.seconds)
+    }
 
-        // Stop current if there is a current
-        if self.current_task.is_some() {
-            self.stop_current()?;
-        }
-    
-        // Set the current task
-        self.current_task = Some(task_id);
+    // GETTERS
 
-        Ok(clone)        
+    pub fn start(&self) -> i64 {
+        self.start
     }
 
-    fn stop_current(&mut self) -> Res<Task> {
-        let task = self.current_task
-            .and_then(|curr| self.task_mut(curr))
-            .ok_or(ResErr::from("Could not find current task in group"))?;
-        let clone = task.clone();
+    pub fn end(&self) -> Option<i64> {
+        self.end
+    }
 
-        // Stop the task
-        task.stop();        
+    pub fn note(&self) -> Option<&String> {
+        self.note.as_ref()
+    }
 
-        // Reset the current task
-        self.current_task = None;
+    pub fn kind(&self) -> SessionKind {
+        self.kind
+    }
 
-        Ok(clone)
+    pub fn timebox(&self) -> Option<Timebox> {
+        self.timebox
     }
+}
 
-    fn complete_task(&mut self, task_id: Option<usize>) -> Res<Task> {
-        let id = task_id
-            .or_else(|| self.current_task)
-            .ok_or(ResErr::from("No task or current task!"))?;
-        
-        let task = self.task_mut(id)
-            .ok_or(ResErr::from("Could not find task in group!"))?;
+/// A break taken from a task, from pausing it until it is resumed.
+/// A pause without an end is still going
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Pause {
+    start: i64,
+    end: Option<i64>
+}
 
-        task.complete();
-        let clone = task.clone();
-    
-        Ok(clone)
+impl Pause {
+    /// Length of the break in seconds.
+    /// A break still going is measured up until now
+    pub fn duration(&self) -> i64 {
+        self.end.unwrap_or_else(time::timestamp) - self.start
     }
 
-    /// Get the task with id: task_id as mutable from this group
-    fn task_mut(&mut self, task_id: usize) -> Option<&mut Task> {
-        for task in &mut self.tasks {
-            if task.id == task_id {
-                return Some(task);
-            }
-        }
+    // GETTERS
 
-        None
+    pub fn start(&self) -> i64 {
+        self.start
     }
 
+    pub fn end(&self) -> Option<i64> {
+        self.end
+    }
+}
+
+/// Refers to a task in a group
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct TaskRef {
+    group: usize,
+    task: usize
+}
+
+impl TaskRef {
+
     // GETTERS
 
-    pub fn name(&self) -> &String {
-        &self.name
+    pub fn group(&self) -> usize {
+        self.group
     }
 
-    pub fn id(&self) -> usize {
-        self.id
+    pub fn task(&self) -> usize {
+        self.task
     }
 }
 
-/// Represents an individual task to complete.
-/// Holds data necessary in computing time tracked
-/// for a task
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Task {
-    id: usize,
-    name: String,
-    started_date: Option<i64>,
-    tracked: Option<i64>,
-    is_complete: bool
+/// How unfinished tasks are carried into a new day's group
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CarryMode {
+    /// The task stays open in the earlier group too
+    Copy,
+
+    /// The task is closed off in the earlier group
+    Move
 }
 
-impl Task {
-    fn new(id: usize, name: String) -> Self {
-        Task {
-            id: id,
-            name: name,
-            started_date: None,
-            tracked: None,
-            is_complete: false
+impl CarryMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CarryMode::Copy => "copy",
+            CarryMode::Move => "move"
         }
     }
+}
 
-    /// Give the Task a timestamp as started_date
-    /// This timestamp represents the time a task started in its current run
-    fn start(&mut self) {
-        self.started_date = Some(time::timestamp());
+impl FromStr for CarryMode {
+    type Err = ResErr;
 
-        // Un-complete the task if it is started
-        self.is_complete = false;
+    fn from_str(s: &str) -> Res<CarryMode> {
+        match s {
+            "copy" => Ok(CarryMode::Copy),
+            "move" => Ok(CarryMode::Move),
+            _ => Err(ResErr::from(format!("Unknown carry mode: {} (expected copy or move)", s)))
+        }
     }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
 
-    /// Stop the task.
-    /// This will erase the started_date timestamp and
-    /// append tracked time to the tracked field
-    fn stop(&mut self) {
-        let tracked = self.tracked.unwrap_or(0);
+/// The task that was paused, to resume
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Paused {
+    group: usize,
+    task: usize
+}
 
-        if let Some(started) = self.started_date {
-            let now = time::timestamp();
-            self.tracked = Some(tracked + (now - started));
-        }
+/// A correction to the time tracked on a task, made by hand.
+/// Negative adjustments take time away
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Adjustment {
+    at: i64,
+    seconds: i64,
+    note: Option<String>
+}
+
+impl Adjustment {
+
+    // GETTERS
 
-        self.started_date = None
+    pub fn at(&self) -> i64 {
+        self.at
     }
 
-    /// Complete the task
-    /// Will the stop the current task, and mark as cimplete
-    fn complete(&mut self) {
-        // Stop the task (it could be currently running)
-        self.stop();
-        self.is_complete = true;
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    pub fn note(&self) -> Option<&String> {
+        self.note.as_ref()
     }
 }
 
@@ -416,7 +2329,7 @@ impl PartialEq for Task {
 impl TableDisplay for Manager {
 
     fn header(&self) -> Row {
-        row!["ID", "Group"]
+        row!["ID", "Group", "UID"]
     }
 
     fn rows(&self) -> Vec<Row> {
@@ -439,7 +2352,8 @@ impl TableDisplay for Manager {
 
             let v = vec![
                 style(Cell::new(&g.id.to_string()), is_current),
-                style(Cell::new(&g.name), is_current)
+                style(Cell::new(&g.name), is_current),
+                style(Cell::new(&format!("{}{}", UID_PREFIX, g.uid)), is_current)
             ];
 
             rows.push(Row::new(v));
@@ -452,13 +2366,33 @@ impl TableDisplay for Manager {
 impl TableDisplay for Group {
     
     fn header(&self) -> Row {
-        row!["ID", "Task", "Started", "Time Tracked"]
+        let mut header = row!["ID", "UID", "Task", "Started", "Time Tracked"];
+
+        // Only shown once breaks are taken in the group
+        if self.has_breaks() {
+            header.add_cell(Cell::new("Breaks"));
+        }
+
+        // Only shown once pomodoros are used in the group
+        if self.has_pomodoros() {
+            header.add_cell(Cell::new("Pomodoros"));
+        }
+
+        header
     }
 
     fn rows(&self) -> Vec<Row> {
+        let has_breaks = self.has_breaks();
+        let has_pomodoros = self.has_pomodoros();
+
         let mut rows: Vec<Row> = Vec::new();
         for e in &self.tasks {  
-            rows.append(&mut e.rows());
+            let mut row = e.row(has_breaks);
+            if has_pomodoros {
+                row.add_cell(Cell::new(&e.pomodoros().to_string()));
+            }
+
+            rows.push(row);
         }
 
         rows
@@ -467,13 +2401,25 @@ impl TableDisplay for Group {
 
 impl TableDisplay for Task {
     fn header(&self) -> Row {
-        row!["ID", "Task", "Started", "Time Tracked"]
+        let mut header = row!["ID", "UID", "Task", "Started", "Time Tracked"];
+
+        if !self.pauses.is_empty() {
+            header.add_cell(Cell::new("Breaks"));
+        }
+
+        header
     }
 
     fn rows(&self) -> Vec<Row> {
-        let mut rows: Vec<Row> = Vec::new();
+        vec![self.row(!self.pauses.is_empty())]
+    }
+}
 
-        let is_started = self.started_date.is_some();
+impl Task {
+    /// The task as a table row, with its time on breaks if shown
+    fn row(&self, breaks: bool) -> Row {
+        let current = self.current_session();
+        let is_started = current.is_some();
         let is_complete = self.is_complete;
 
         let style = |cell: Cell| -> Cell {
@@ -492,37 +2438,399 @@ impl TableDisplay for Task {
 
         // Display complete/stopped depending on the complete status
         // of the task
+        let is_paused = self.pauses.iter().any(|p| p.end.is_none());
         let started_display = || -> String {
-            return if is_complete { String::from("COMPLETE") } else { String::from("STOPPED") }
+            if is_complete {
+                String::from("COMPLETE")
+            } else if is_paused {
+                String::from("PAUSED")
+            } else if self.carried {
+                String::from("CARRIED")
+            } else {
+                String::from("STOPPED")
+            }
         };
 
         let v = vec![
             style(Cell::new(&self.id.to_string())),
+            style(Cell::new(&format!("{}{}", UID_PREFIX, self.uid))),
             style(Cell::new(&self.name)),
             style(Cell::new(
-                &self.started_date
-                    .map(|sd| time::to_local_datetime(sd)
+                &current
+                    .map(|s| time::to_local_datetime(s.start)
                         .format("%B %e %r %Y")
                         .to_string())
-                    .unwrap_or(started_display())
+                    .unwrap_or_else(started_display)
             )),
             style(Cell::new(
-                &self.started_date
-                        .map(|sd| {
-                            
-                            let tracked = self.tracked.unwrap_or(0);
-                            let now = time::timestamp();
-                            // now minus sd plus tracked
-                            
-                            time::duration_str(tracked + (now - sd))
-                        })
-                        .or_else(|| self.tracked.map(|sd| time::duration_str(sd)))
-                        .unwrap_or(String::from("NONE"))
+                &if self.sessions.is_empty() && self.adjustments.is_empty() {
+                    String::from("NONE")
+                } else if self.adjusted() != 0 {
+                    // Flag totals that include corrections made by hand
+                    format!("{} (adjusted)", time::duration_str(self.tracked()))
+                } else {
+                    time::duration_str(self.tracked())
+                }
             ))
         ];
 
-        rows.push(Row::new(v));
+        let mut row = Row::new(v);
+        if breaks {
+            row.add_cell(style(Cell::new(
+                &if self.pauses.is_empty() { String::from("NONE") } else { time::duration_str(self.paused()) }
+            )));
+        }
+
+        row
+    }
+}
 
-        rows
+/// The sessions of a task, numbered so they can be edited,
+/// followed by its breaks and adjustments
+pub struct SessionList(pub Task);
+
+impl TableDisplay for SessionList {
+    fn header(&self) -> Row {
+        row!["#", "Start", "End", "Duration", "Kind", "Note"]
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let format = |stamp: i64| time::to_local_datetime(stamp)
+            .format("%B %e %r %Y")
+            .to_string();
+
+        let sessions = self.0.sessions.iter()
+            .enumerate()
+            .map(|(i, s)| row![
+                i + 1,
+                format(s.start),
+                s.end.map(format).unwrap_or_else(|| String::from("RUNNING")),
+                time::duration_str(s.duration()),
+                match s.kind {
+                    SessionKind::Timed => "timed",
+                    SessionKind::Logged => "logged"
+                },
+                s.note.clone().unwrap_or_default()
+            ]);
+
+        // Breaks and adjustments aren't sessions, so they have no number
+        let pauses = self.0.pauses.iter()
+            .map(|p| row![
+                "",
+                format(p.start),
+                p.end.map(format).unwrap_or_else(|| String::from("RUNNING")),
+                time::duration_str(p.duration()),
+                "break",
+                ""
+            ]);
+
+        let adjustments = self.0.adjustments.iter()
+            .map(|a| row![
+                "",
+                format(a.at),
+                "",
+                time::duration_str(a.seconds),
+                "adjustment",
+                a.note.clone().unwrap_or_default()
+            ]);
+
+        sessions.chain(pauses).chain(adjustments).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::edit;
+    use crate::storage::MemoryStorage;
+
+    fn open(storage: &MemoryStorage) -> Manager {
+        Manager::with_storage(Box::new(storage.clone())).unwrap()
+    }
+
+    /// Add yesterday's group, and use it
+    fn use_yesterday(manager: &mut Manager) -> usize {
+        let name = (time::today_local() - Duration::days(1)).format(DATE_FORMAT).to_string();
+        let id = manager.add_group(name).unwrap().id();
+        manager.use_group(id).unwrap();
+
+        id
+    }
+
+    fn add(manager: &mut Manager, name: &str) -> TaskRef {
+        manager.add_task(String::from(name)).unwrap();
+        manager.find_task(name).unwrap()
+    }
+
+    #[test]
+    fn round_trip_on_memory_storage() {
+        let storage = MemoryStorage::new();
+
+        let mut manager = open(&storage);
+        let task = add(&mut manager, "a");
+        manager.start_task(task, Some(String::from("note")), None, None, false).unwrap();
+        let saved = serde_json::to_value(&manager).unwrap();
+        manager.commit().unwrap();
+
+        assert_eq!(serde_json::to_value(open(&storage)).unwrap(), saved);
+    }
+
+    #[test]
+    fn replays_events_after_the_snapshot() {
+        let storage = MemoryStorage::new();
+
+        let mut manager = open(&storage);
+        add(&mut manager, "a");
+        manager.commit().unwrap();
+        let snapshot = storage.snapshot();
+
+        // Too few events for another snapshot, so these are only in the log
+        let mut manager = open(&storage);
+        add(&mut manager, "b");
+        manager.commit().unwrap();
+        assert_eq!(storage.snapshot(), snapshot);
+
+        let mut manager = open(&storage);
+        let names: Vec<&String> = manager.group().unwrap().tasks().iter().map(|t| t.name()).collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[test]
+    fn undo_and_redo_replay_the_log() {
+        let storage = MemoryStorage::new();
+        let names = |manager: &mut Manager| manager.group().unwrap().tasks().iter()
+            .map(|t| t.name().clone())
+            .collect::<Vec<_>>();
+
+        let mut manager = open(&storage);
+        add(&mut manager, "a");
+        manager.commit().unwrap();
+
+        let mut manager = open(&storage);
+        add(&mut manager, "b");
+        let name = (time::today_local() - Duration::days(1)).format(DATE_FORMAT).to_string();
+        let id = manager.add_group(name).unwrap().id();
+        manager.commit().unwrap();
+
+        let mut manager = open(&storage);
+        manager.archive_groups(false, vec![id]).unwrap();
+        manager.commit().unwrap();
+
+        // Entries only point into the log, they don't copy the state
+        let history = storage.clone().load_document(history::DOCUMENT).unwrap().unwrap();
+        assert!(history["undo"].as_array().unwrap().iter().all(|e| e.get("state").is_none()));
+
+        let mut manager = open(&storage);
+        manager.undo().unwrap();
+        manager.undo().unwrap();
+        assert_eq!(names(&mut manager), ["a"]);
+        assert!(manager.archive().unwrap().is_empty());
+        manager.commit().unwrap();
+
+        let mut manager = open(&storage);
+        manager.redo().unwrap();
+        manager.redo().unwrap();
+        assert_eq!(names(&mut manager), ["a", "b"]);
+        assert!(manager.find_group(&id.to_string()).is_err());
+        assert!(!manager.archive().unwrap().is_empty());
+    }
+
+    #[test]
+    fn as_of_replays_backdated_events_in_order() {
+        let mut manager = open(&MemoryStorage::new());
+        let task = add(&mut manager, "a");
+        let now = time::timestamp();
+        manager.start_task(task, None, None, Some(now - 10 * 60), false).unwrap();
+
+        // The start was backdated to before then, but recorded after
+        let mut past = manager.as_of(now - 5 * 60).unwrap();
+        assert!(past.group().is_err());
+
+        let mut past = manager.as_of(time::timestamp()).unwrap();
+        assert_eq!(past.group().unwrap().current_task(), Some(task.task));
+        let started = past.task(task).unwrap().current_session().unwrap().start();
+        assert_eq!(started, now - 10 * 60);
+    }
+
+    #[test]
+    fn unique_ids_stay_with_tasks() {
+        let mut manager = open(&MemoryStorage::new());
+        let a = add(&mut manager, "a");
+        let b = add(&mut manager, "b");
+        let uid = manager.task(b).unwrap().uid();
+
+        manager.remove_task(a).unwrap();
+        manager.minimize_ids().unwrap();
+
+        let found = manager.find_task(&format!("{}{}", UID_PREFIX, uid)).unwrap();
+        assert_eq!(manager.task(found).unwrap().name(), "b");
+    }
+
+    #[test]
+    fn numbers_only_match_ids() {
+        let mut manager = open(&MemoryStorage::new());
+        add(&mut manager, "Fix bug 17");
+        let docs = add(&mut manager, "Write docs");
+
+        assert_eq!(manager.find_task("2").unwrap(), docs);
+        assert!(manager.find_task("7").is_err());
+        assert!(manager.find_task("17").is_err());
+        assert!(manager.find_group("5").is_err());
+    }
+
+    #[test]
+    fn strict_finds_skip_fuzzy_matches() {
+        let mut manager = open(&MemoryStorage::new());
+        let docs = add(&mut manager, "Write docs");
+
+        assert_eq!(manager.find_task("wd").unwrap(), docs);
+        assert!(manager.find_task_strict("wd").is_err());
+        assert_eq!(manager.find_task_strict("docs").unwrap(), docs);
+    }
+
+    #[test]
+    fn stop_finds_the_running_task_in_another_group() {
+        let mut manager = open(&MemoryStorage::new());
+        use_yesterday(&mut manager);
+        let task = add(&mut manager, "a");
+        manager.start_task(task, None, None, None, false).unwrap();
+        manager.reset_group().unwrap();
+
+        let stopped = manager.stop_current(None).unwrap();
+        assert_eq!(stopped.name(), "a");
+        assert!(stopped.current_session().is_none());
+        assert!(manager.stop_current(None).is_err());
+    }
+
+    #[test]
+    fn editing_in_a_running_session_stops_other_groups() {
+        let mut manager = open(&MemoryStorage::new());
+        let now = time::timestamp();
+        let task = add(&mut manager, "a");
+        manager.start_task(task, None, None, Some(now - 60 * 60), false).unwrap();
+
+        let yesterday = use_yesterday(&mut manager);
+        add(&mut manager, "b");
+        let text = format!(
+            "task 1: b\n  session: {} -> running\n",
+            time::to_local_datetime(now - 30 * 60).format("%Y-%m-%d %H:%M:%S")
+        );
+
+        let (_, stopped) = manager.replace_tasks(yesterday, edit::parse(&text, now).unwrap()).unwrap();
+        assert_eq!(stopped.len(), 1);
+        assert_eq!(stopped[0].sessions()[0].end(), Some(now - 30 * 60));
+        assert!(manager.task(task).unwrap().current_session().is_none());
+
+        // Saving it again leaves other running tasks alone
+        manager.start_task(task, None, None, None, true).unwrap();
+        let (_, stopped) = manager.replace_tasks(yesterday, edit::parse(&text, now).unwrap()).unwrap();
+        assert!(stopped.is_empty());
+        assert!(manager.task(task).unwrap().current_session().is_some());
+    }
+
+    #[test]
+    fn start_returns_the_task_stopped_in_the_same_group() {
+        let mut manager = open(&MemoryStorage::new());
+        let a = add(&mut manager, "a");
+        let b = add(&mut manager, "b");
+
+        manager.start_task(a, None, None, Some(time::timestamp() - 60), false).unwrap();
+        let (started, stopped) = manager.start_task(b, None, None, None, false).unwrap();
+
+        assert_eq!(started.name(), "b");
+        assert_eq!(stopped.len(), 1);
+        assert_eq!(stopped[0].name(), "a");
+    }
+
+    #[test]
+    fn backdated_start_is_split_across_days() {
+        let mut manager = open(&MemoryStorage::new());
+        manager.set_split_days(true).unwrap();
+
+        let yesterday = use_yesterday(&mut manager);
+        let task = add(&mut manager, "a");
+        let at = time::parse_at(
+            &format!("{} 23:00", (time::today_local() - Duration::days(1)).format("%Y-%m-%d")),
+            time::timestamp()
+        ).unwrap();
+
+        let (started, _) = manager.start_task(task, None, None, Some(at), false).unwrap();
+        assert!(started.current_session().is_some());
+
+        // The part after midnight runs in today's group
+        assert_eq!(manager.group_with_id(yesterday).unwrap().current_task(), None);
+        manager.reset_group().unwrap();
+        assert_eq!(manager.group().unwrap().current_task(), Some(started.id()));
+
+        let stopped = manager.stop_current(None).unwrap();
+        assert_eq!(stopped.uid(), started.uid());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn moving_a_running_task_keeps_it_running() {
+        let mut manager = open(&MemoryStorage::new());
+        let today = manager.group().unwrap().id();
+        add(&mut manager, "first");
+        let task = add(&mut manager, "a");
+        manager.start_task(task, None, None, None, false).unwrap();
+
+        let yesterday = use_yesterday(&mut manager);
+        let moved = manager.move_task(task, yesterday).unwrap();
+
+        assert_eq!(moved.id(), 1);
+        assert_eq!(manager.group_with_id(today).unwrap().current_task(), None);
+        assert_eq!(manager.group_with_id(yesterday).unwrap().current_task(), Some(1));
+        assert_eq!(manager.stop_current(None).unwrap().uid(), moved.uid());
+    }
+
+    #[test]
+    fn copying_a_running_task_does_not_run_the_copy() {
+        let mut manager = open(&MemoryStorage::new());
+        let task = add(&mut manager, "a");
+        manager.log_time(task, 60, None, None).unwrap();
+        manager.start_task(task, None, None, None, false).unwrap();
+
+        let yesterday = use_yesterday(&mut manager);
+        let copy = manager.copy_task(task, yesterday).unwrap();
+
+        assert_eq!(copy.sessions().len(), 1);
+        assert_ne!(copy.uid(), manager.task(task).unwrap().uid());
+        assert_eq!(manager.group_with_id(yesterday).unwrap().current_task(), None);
+    }
+
+    #[test]
+    fn carrying_a_running_task_moves_the_timer() {
+        let mut manager = open(&MemoryStorage::new());
+        let today = manager.group().unwrap().id();
+
+        let yesterday = use_yesterday(&mut manager);
+        let task = add(&mut manager, "a");
+        manager.start_task(task, None, None, Some(time::timestamp() - 60), false).unwrap();
+
+        let carried = manager.carry_tasks(today, CarryMode::Move).unwrap();
+        assert_eq!(carried.len(), 1);
+        assert!(carried[0].current_session().is_some());
+        assert_eq!(carried[0].origin(), Some(task));
+
+        let origin = manager.task(task).unwrap();
+        assert!(origin.is_carried());
+        assert!(origin.current_session().is_none());
+        assert_eq!(manager.group_with_id(yesterday).unwrap().current_task(), None);
+    }
+
+    #[test]
+    fn renaming_keeps_names_unique() {
+        let mut manager = open(&MemoryStorage::new());
+        let today = manager.group().unwrap().id();
+        let other = manager.add_group(String::from("other")).unwrap().id();
+
+        assert!(manager.rename_group(other, String::from("taken")).is_ok());
+        assert!(manager.add_group(String::from("taken")).is_err());
+        assert!(manager.rename_group(other, default_group_name()).is_err());
+
+        // Today's group can't be renamed while it is the default
+        assert!(manager.rename_group(today, String::from("renamed")).is_err());
+    }
+}
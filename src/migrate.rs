@@ -0,0 +1,242 @@
+///
+/// Upgrades older data documents to the current schema.
+/// Migrations work on the raw JSON so that the data structs only
+/// ever need to understand the latest format.
+/// 
+use serde_json::{json, Map, Value};
+
+use crate::{Res, ResErr};
+use crate::time;
+
+/// The schema version written by this version of track
+pub const SCHEMA_VERSION: u64 = 3;
+
+const VERSION_KEY: &str = "version";
+
+/// A single step, upgrading a document from version N to N + 1
+type Migration = fn(&mut Map<String, Value>) -> Res<()>;
+
+/// Ordered migrations. The migration at index N upgrades version N to N + 1.
+/// Documents written before versioning was introduced are version 0.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [
+    v0_sessions,
+    v1_event_seq,
+    v2_uids
+];
+
+/// Get the schema version of a document
+pub fn version(doc: &Value) -> u64 {
+    doc.get(VERSION_KEY)
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0)
+}
+
+/// Upgrade the document step by step until it reaches SCHEMA_VERSION.
+/// Returns true if any migration was applied.
+pub fn migrate(doc: &mut Value) -> Res<bool> {
+    let start = version(doc);
+
+    if start > SCHEMA_VERSION {
+        return Err(ResErr::from(format!(
+            "Data file has schema version {}, but this version of track only supports up to {}",
+            start, SCHEMA_VERSION
+        )));
+    }
+
+    let obj = doc.as_object_mut()
+        .ok_or_else(|| ResErr::from("Data file is not a JSON object"))?;
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(start as usize) {
+        migration(obj)?;
+        obj.insert(String::from(VERSION_KEY), json!(from as u64 + 1));
+    }
+
+    Ok(start < SCHEMA_VERSION)
+}
+
+/// Iterate every task object in the document
+fn tasks_mut(doc: &mut Map<String, Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    doc.get_mut("groups")
+        .and_then(|g| g.as_array_mut())
+        .into_iter()
+        .flatten()
+        .filter_map(|g| g.get_mut("tasks").and_then(|t| t.as_array_mut()))
+        .flatten()
+        .filter_map(|t| t.as_object_mut())
+}
+
+// --- MIGRATIONS ---
+
+/// 0 -> 1
+/// Tasks used to only store the start of the current run (started_date)
+/// and a cumulative sum (tracked). Move both into the sessions list.
+/// The tracked sum has no interval attached to it, so it becomes a
+/// single session ending at the time of migration, or where the
+/// current run started, so the two don't overlap.
+fn v0_sessions(doc: &mut Map<String, Value>) -> Res<()> {
+    let now = time::timestamp();
+
+    for task in tasks_mut(doc) {
+        let started = task.remove("started_date").and_then(|v| v.as_i64());
+        let tracked = task.remove("tracked").and_then(|v| v.as_i64());
+
+        let mut sessions = match task.remove("sessions") {
+            Some(Value::Array(sessions)) => sessions,
+            _ => Vec::new()
+        };
+
+        if let Some(tracked) = tracked {
+            let end = started.unwrap_or(now);
+            sessions.push(json!({
+                "start": end - tracked,
+                "end": end,
+                "note": "Migrated"
+            }));
+        }
+
+        if let Some(started) = started {
+            sessions.push(json!({
+                "start": started,
+                "end": null,
+                "note": null
+            }));
+        }
+
+        task.insert(String::from("sessions"), Value::Array(sessions));
+    }
+
+    Ok(())
+}
+
+/// 1 -> 2
+/// The manager records the sequence number of the last event it applied,
+/// so it can be used as a snapshot of the event log.
+/// Data from before the event log has applied none.
+fn v1_event_seq(doc: &mut Map<String, Value>) -> Res<()> {
+    // Only the manager has this, not the archive
+    if doc.contains_key("next_group") && !doc.contains_key("event_seq") {
+        doc.insert(String::from("event_seq"), json!(0));
+    }
+
+    Ok(())
+}
+
+/// 2 -> 3
+/// Groups and tasks have a unique ID that never changes, unlike their
+/// numbers. Number the existing ones in order, and continue from there.
+/// Archived groups are given theirs when they are unarchived.
+fn v2_uids(doc: &mut Map<String, Value>) -> Res<()> {
+    // Only the manager has this, not the archive
+    if !doc.contains_key("next_group") || doc.contains_key("next_uid") {
+        return Ok(());
+    }
+
+    let mut next_uid: u64 = 1;
+    let mut take_uid = || {
+        next_uid += 1;
+        json!(next_uid - 1)
+    };
+
+    let groups = doc.get_mut("groups")
+        .and_then(|g| g.as_array_mut())
+        .into_iter()
+        .flatten()
+        .filter_map(|g| g.as_object_mut());
+
+    for group in groups {
+        group.insert(String::from("uid"), take_uid());
+
+        let tasks = group.get_mut("tasks")
+            .and_then(|t| t.as_array_mut())
+            .into_iter()
+            .flatten()
+            .filter_map(|t| t.as_object_mut());
+
+        for task in tasks {
+            task.insert(String::from("uid"), take_uid());
+        }
+    }
+
+    doc.insert(String::from("next_uid"), json!(next_uid));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sessions(doc: &Map<String, Value>) -> Vec<Value> {
+        doc["groups"][0]["tasks"][0]["sessions"].as_array().cloned().unwrap()
+    }
+
+    fn v0_doc(task: Value) -> Map<String, Value> {
+        match json!({ "next_group": 2, "groups": [{ "id": 1, "name": "g", "tasks": [task] }] }) {
+            Value::Object(doc) => doc,
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn v0_sessions_keeps_tracked_time() {
+        let mut doc = v0_doc(json!({ "id": 1, "name": "t", "tracked": 600, "is_complete": false }));
+        v0_sessions(&mut doc).unwrap();
+
+        let sessions = sessions(&doc);
+        assert_eq!(sessions.len(), 1);
+
+        let (start, end) = (sessions[0]["start"].as_i64().unwrap(), sessions[0]["end"].as_i64().unwrap());
+        assert_eq!(end - start, 600);
+        assert_eq!(sessions[0]["note"], json!("Migrated"));
+
+        let task = doc["groups"][0]["tasks"][0].as_object().unwrap();
+        assert!(!task.contains_key("tracked") && !task.contains_key("started_date"));
+    }
+
+    #[test]
+    fn v0_sessions_running_task_does_not_overlap() {
+        let started = time::timestamp() - 30 * 60;
+        let mut doc = v0_doc(json!({
+            "id": 1, "name": "t", "started_date": started, "tracked": 2 * 60 * 60, "is_complete": false
+        }));
+        v0_sessions(&mut doc).unwrap();
+
+        let sessions = sessions(&doc);
+        assert_eq!(sessions.len(), 2);
+
+        // The tracked time ends where the current run starts
+        assert_eq!(sessions[0]["end"], json!(started));
+        assert_eq!(sessions[0]["start"], json!(started - 2 * 60 * 60));
+        assert_eq!(sessions[1]["start"], json!(started));
+        assert_eq!(sessions[1]["end"], Value::Null);
+    }
+
+    #[test]
+    fn v0_sessions_leaves_untracked_tasks_empty() {
+        let mut doc = v0_doc(json!({ "id": 1, "name": "t", "is_complete": false }));
+        v0_sessions(&mut doc).unwrap();
+
+        assert!(sessions(&doc).is_empty());
+    }
+
+    #[test]
+    fn migrate_upgrades_to_the_latest_version() {
+        let mut doc = Value::Object(v0_doc(json!({ "id": 1, "name": "t", "tracked": 60, "is_complete": false })));
+
+        assert!(migrate(&mut doc).unwrap());
+        assert_eq!(version(&doc), SCHEMA_VERSION);
+        assert_eq!(doc["event_seq"], json!(0));
+        assert_eq!(doc["groups"][0]["uid"], json!(1));
+        assert_eq!(doc["groups"][0]["tasks"][0]["uid"], json!(2));
+        assert_eq!(doc["next_uid"], json!(3));
+
+        // Already up to date
+        assert!(!migrate(&mut doc).unwrap());
+    }
+
+    #[test]
+    fn migrate_rejects_newer_versions() {
+        let mut doc = json!({ "version": SCHEMA_VERSION + 1 });
+        assert!(migrate(&mut doc).is_err());
+    }
+}
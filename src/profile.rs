@@ -0,0 +1,128 @@
+///
+/// Named profiles, each with their own data file.
+/// The default profile lives directly in the base directory,
+/// any other profile lives in base/profiles/<name>.
+/// 
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use prettytable::{Attr, color, Cell, Row, row};
+
+use crate::{Res, ResErr};
+use crate::table::TableDisplay;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const PROFILES_DIR: &str = "profiles";
+
+#[derive(Debug, Clone)]
+pub struct Profile {
+    name: String,
+    dir: PathBuf
+}
+
+impl Profile {
+
+    /// Resolve the profile with the given name inside base.
+    /// No name gives the default profile
+    pub fn resolve(base: &Path, name: Option<&str>) -> Res<Profile> {
+        let name = name.unwrap_or(DEFAULT_PROFILE);
+        validate_name(name)?;
+
+        let dir = if name == DEFAULT_PROFILE {
+            base.to_path_buf()
+        } else {
+            base.join(PROFILES_DIR).join(name)
+        };
+
+        Ok(Profile { name: String::from(name), dir })
+    }
+
+    /// Make sure the directory of this profile exists
+    pub fn create(&self) -> Res<()> {
+        fs::create_dir_all(&self.dir)?;
+        Ok(())
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT_PROFILE
+    }
+
+    // GETTERS
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// All profiles that exist in base, along with which one is active
+pub struct ProfileList {
+    current: String,
+    names: Vec<String>
+}
+
+impl ProfileList {
+    pub fn load(base: &Path, current: &Profile) -> Res<ProfileList> {
+        let mut names = vec![String::from(DEFAULT_PROFILE)];
+
+        let profiles_dir = base.join(PROFILES_DIR);
+        if profiles_dir.exists() {
+            let mut found: Vec<String> = Vec::new();
+
+            for entry in fs::read_dir(profiles_dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    found.push(entry.file_name().to_string_lossy().into_owned());
+                }
+            }
+
+            found.sort();
+            names.append(&mut found);
+        }
+
+        Ok(ProfileList { current: current.name.clone(), names })
+    }
+}
+
+/// Profile names become directory names, so keep them simple
+fn validate_name(name: &str) -> Res<()> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !valid {
+        return Err(ResErr::from(
+            "Profile names may only contain letters, numbers, '-' and '_'"
+        ));
+    }
+
+    Ok(())
+}
+
+// --- Table Display ---
+
+impl TableDisplay for ProfileList {
+
+    fn header(&self) -> Row {
+        row!["Profile"]
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        self.names.iter()
+            .map(|name| {
+                let cell = Cell::new(name);
+
+                if *name == self.current {
+                    return Row::new(vec![cell
+                        .with_style(Attr::Bold)
+                        .with_style(Attr::ForegroundColor(color::BRIGHT_RED))]);
+                }
+
+                Row::new(vec![cell])
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,118 @@
+//!
+//! Find what a name refers to among a list of candidates.
+//! Names are matched exactly, then by a unique prefix, and then
+//! fuzzily, each ignoring case. The first way that matches wins.
+//!
+
+/// The outcome of looking for a name
+#[derive(Debug, PartialEq)]
+pub enum Found<T> {
+    One(T),
+
+    /// Several candidates match equally well
+    Ambiguous(Vec<T>),
+
+    None
+}
+
+/// How loosely a query may match a name
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Matching {
+    /// Exact, prefix or substring matches only. Used by commands that
+    /// remove data, where a surprising match would be costly
+    Strict,
+
+    /// Also matches subsequences, so "rvw" matches "review"
+    Fuzzy
+}
+
+/// Find the candidate whose name matches the query
+pub fn find<T: Clone>(query: &str, candidates: &[(T, &str)], matching: Matching) -> Found<T> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Found::None;
+    }
+
+    let matchers: [&dyn Fn(&str) -> bool; 4] = [
+        &|name| name == query,
+        &|name| name.starts_with(&query),
+        &|name| name.contains(&query),
+        &|name| is_subsequence(&query, name)
+    ];
+
+    let tried = match matching {
+        Matching::Strict => &matchers[..3],
+        Matching::Fuzzy => &matchers[..]
+    };
+
+    for matches in tried.iter() {
+        let found: Vec<T> = candidates.iter()
+            .filter(|(_, name)| matches(&name.to_lowercase()))
+            .map(|(c, _)| c.clone())
+            .collect();
+
+        match found.len() {
+            0 => continue,
+            1 => return Found::One(found[0].clone()),
+            _ => return Found::Ambiguous(found)
+        }
+    }
+
+    Found::None
+}
+
+/// Whether the characters of the query appear in the name in order,
+/// so "rvw" matches "review"
+fn is_subsequence(query: &str, name: &str) -> bool {
+    let mut chars = name.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [(usize, &str); 4] = [
+        (1, "Review PR"),
+        (2, "review"),
+        (3, "Write docs"),
+        (4, "Deploy")
+    ];
+
+    #[test]
+    fn exact_match_wins_over_prefix() {
+        assert_eq!(find("review", &NAMES, Matching::Fuzzy), Found::One(2));
+        assert_eq!(find("REVIEW pr", &NAMES, Matching::Fuzzy), Found::One(1));
+    }
+
+    #[test]
+    fn unique_prefix() {
+        assert_eq!(find("wri", &NAMES, Matching::Fuzzy), Found::One(3));
+        assert_eq!(find("dep", &NAMES, Matching::Fuzzy), Found::One(4));
+    }
+
+    #[test]
+    fn ambiguous_prefix() {
+        assert_eq!(find("rev", &NAMES, Matching::Fuzzy), Found::Ambiguous(vec![1, 2]));
+    }
+
+    #[test]
+    fn contains_then_subsequence() {
+        assert_eq!(find("docs", &NAMES, Matching::Fuzzy), Found::One(3));
+        assert_eq!(find("dpl", &NAMES, Matching::Fuzzy), Found::One(4));
+        assert_eq!(find("wdcs", &NAMES, Matching::Fuzzy), Found::One(3));
+    }
+
+    #[test]
+    fn strict_skips_subsequences() {
+        assert_eq!(find("docs", &NAMES, Matching::Strict), Found::One(3));
+        assert_eq!(find("wdcs", &NAMES, Matching::Strict), Found::None);
+    }
+
+    #[test]
+    fn no_match() {
+        assert_eq!(find("xyz", &NAMES, Matching::Fuzzy), Found::None);
+        assert_eq!(find("  ", &NAMES, Matching::Fuzzy), Found::None);
+        assert_eq!(find("review", &[] as &[(usize, &str)], Matching::Fuzzy), Found::None);
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::{Res, ResErr};
+use crate::archive::Archive;
+use crate::event::Event;
+use crate::file::{FileAccess, FileLock};
+use crate::manager::Manager;
+use crate::migrate;
+use crate::storage::Storage;
+
+pub const ARCHIVE_FILE_NAME: &str = "archive.json";
+pub const EVENTS_FILE_NAME: &str = "events.jsonl";
+
+/// Named documents are kept as <name>.json
+pub fn document_file_name(name: &str) -> String {
+    format!("{}.json", name)
+}
+
+/// Stores the manager as data.json in a directory,
+/// and archived groups as archive.json next to it.
+/// The event log is kept as events.jsonl, one event per line.
+/// The data file is locked for as long as this storage is open.
+#[derive(Debug)]
+pub struct JsonStorage {
+    dir: PathBuf,
+    file_access: FileAccess,
+    archive_access: FileAccess,
+    events_access: FileAccess,
+    _lock: FileLock
+}
+
+impl JsonStorage {
+
+    /// Open the data file in dir, waiting for any other
+    /// track process to release it first
+    pub fn open(dir: &Path) -> Res<JsonStorage> {
+        let file_access = FileAccess::new(dir);
+        let archive_access = FileAccess::with_file(dir, ARCHIVE_FILE_NAME);
+        let events_access = FileAccess::with_file(dir, EVENTS_FILE_NAME);
+        let lock = file_access.lock()?;
+
+        Ok(JsonStorage {
+            dir: dir.to_path_buf(),
+            file_access,
+            archive_access,
+            events_access,
+            _lock: lock
+        })
+    }
+
+    /// Documents can be large and are written often, so they get no backups
+    fn document_access(&self, name: &str) -> FileAccess {
+        FileAccess::with_file(&self.dir, &document_file_name(name)).without_backups()
+    }
+
+    /// Read the file, bringing it up to the current schema first.
+    /// The original is kept around in case a migration goes wrong
+    fn read_migrated(file_access: &FileAccess) -> Res<Value> {
+        let mut doc: Value = file_access.read()?;
+        let old_version = migrate::version(&doc);
+
+        if migrate::migrate(&mut doc)? {
+            file_access.backup(&format!("v{}.bak", old_version))?;
+            file_access.write(&doc)?;
+        }
+
+        Ok(doc)
+    }
+}
+
+impl Storage for JsonStorage {
+
+    fn load(&mut self) -> Res<Option<Manager>> {
+        if !self.file_access.exists() {
+            return Ok(None);
+        }
+
+        let doc = Self::read_migrated(&self.file_access)?;
+        Ok(Some(serde_json::from_value(doc)?))
+    }
+
+    fn save(&mut self, manager: &Manager) -> Res<()> {
+        self.file_access.write(manager)
+    }
+
+    fn load_archive(&mut self) -> Res<Archive> {
+        if !self.archive_access.exists() {
+            return Ok(Archive::new());
+        }
+
+        let doc = Self::read_migrated(&self.archive_access)?;
+        Ok(serde_json::from_value(doc)?)
+    }
+
+    fn save_archive(&mut self, archive: &Archive) -> Res<()> {
+        self.archive_access.write(archive)
+    }
+
+    fn load_document(&mut self, name: &str) -> Res<Option<Value>> {
+        let access = self.document_access(name);
+        if !access.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(access.read()?))
+    }
+
+    fn save_document(&mut self, name: &str, doc: &Value) -> Res<()> {
+        self.document_access(name).write(doc)
+    }
+
+    fn load_events(&mut self, after: u64) -> Res<Vec<Event>> {
+        let lines = self.events_access.read_lines()?;
+        let mut events = Vec::new();
+
+        for (index, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // Skip what the snapshot already holds without parsing it,
+            // since events like restores can be large
+            if Event::seq_of_line(line).filter(|seq| *seq <= after).is_some() {
+                continue;
+            }
+
+            match serde_json::from_str::<Event>(line) {
+                Ok(event) => {
+                    if event.seq() > after {
+                        events.push(event);
+                    }
+                },
+                // A partial line left by a crash during an append
+                Err(_) if index + 1 == lines.len() => break,
+                Err(err) => return Err(ResErr::from(format!(
+                    "Could not read event on line {} of {}: {}", index + 1, EVENTS_FILE_NAME, err
+                )))
+            }
+        }
+
+        Ok(events)
+    }
+
+    fn append_events(&mut self, events: &[Event]) -> Res<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = Vec::new();
+        for event in events {
+            lines.push(serde_json::to_string(event)?);
+        }
+
+        self.events_access.append_lines(&lines)
+    }
+}
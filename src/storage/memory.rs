@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use serde_json::Value;
+
+use crate::Res;
+use crate::archive::Archive;
+use crate::event::Event;
+use crate::manager::Manager;
+use crate::storage::Storage;
+
+/// Keeps the manager in memory, never touching the data directory.
+///
+/// Clones share the same store, so a clone can be kept around to
+/// inspect what was saved, or to open another manager on the same data.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    data: Rc<RefCell<Option<Value>>>,
+    archive: Rc<RefCell<Option<Value>>>,
+    documents: Rc<RefCell<HashMap<String, Value>>>,
+    events: Rc<RefCell<Vec<Event>>>
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The last saved manager, as the JSON document it would be stored as
+    pub fn snapshot(&self) -> Option<Value> {
+        self.data.borrow().clone()
+    }
+}
+
+impl Storage for MemoryStorage {
+
+    fn load(&mut self) -> Res<Option<Manager>> {
+        match self.data.borrow().as_ref() {
+            Some(doc) => Ok(Some(serde_json::from_value(doc.clone())?)),
+            None => Ok(None)
+        }
+    }
+
+    fn save(&mut self, manager: &Manager) -> Res<()> {
+        *self.data.borrow_mut() = Some(serde_json::to_value(manager)?);
+        Ok(())
+    }
+
+    fn load_archive(&mut self) -> Res<Archive> {
+        match self.archive.borrow().as_ref() {
+            Some(doc) => Ok(serde_json::from_value(doc.clone())?),
+            None => Ok(Archive::new())
+        }
+    }
+
+    fn save_archive(&mut self, archive: &Archive) -> Res<()> {
+        *self.archive.borrow_mut() = Some(serde_json::to_value(archive)?);
+        Ok(())
+    }
+
+    fn load_document(&mut self, name: &str) -> Res<Option<Value>> {
+        Ok(self.documents.borrow().get(name).cloned())
+    }
+
+    fn save_document(&mut self, name: &str, doc: &Value) -> Res<()> {
+        self.documents.borrow_mut().insert(String::from(name), doc.clone());
+        Ok(())
+    }
+
+    fn load_events(&mut self, after: u64) -> Res<Vec<Event>> {
+        Ok(self.events.borrow().iter()
+            .filter(|e| e.seq() > after)
+            .cloned()
+            .collect())
+    }
+
+    fn append_events(&mut self, events: &[Event]) -> Res<()> {
+        self.events.borrow_mut().extend_from_slice(events);
+        Ok(())
+    }
+}
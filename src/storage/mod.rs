@@ -0,0 +1,185 @@
+///
+/// Storage backends the Manager can be loaded from and committed to.
+/// 
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::{Res, ResErr};
+use crate::archive::Archive;
+use crate::event::Event;
+use crate::file;
+use crate::history;
+use crate::manager::Manager;
+
+mod json;
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use json::JsonStorage;
+pub use memory::MemoryStorage;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+/// File name of the SQLite database, used to detect which backend a
+/// directory uses even when built without the sqlite feature
+const SQLITE_FILE_NAME: &str = "data.db";
+
+/// Every named document kept alongside the main data
+const DOCUMENTS: &[&str] = &[history::DOCUMENT];
+
+/// A place a Manager is persisted.
+///
+/// A storage is opened once per unit of work: the manager is loaded,
+/// changed and then saved. Anything the storage needs to hold in between,
+/// like a lock, should be released when it is dropped.
+pub trait Storage: Debug {
+
+    /// Load the stored manager, or None if nothing has been stored yet
+    fn load(&mut self) -> Res<Option<Manager>>;
+
+    /// Persist the manager, replacing whatever was stored before
+    fn save(&mut self, manager: &Manager) -> Res<()>;
+
+    /// Load the archived groups. Nothing archived yet gives an empty archive
+    fn load_archive(&mut self) -> Res<Archive>;
+
+    /// Persist the archived groups, replacing whatever was stored before
+    fn save_archive(&mut self, archive: &Archive) -> Res<()>;
+
+    /// Load a named document kept alongside the main data,
+    /// or None if it hasn't been saved yet
+    fn load_document(&mut self, name: &str) -> Res<Option<Value>>;
+
+    /// Persist a named document, replacing whatever was stored before
+    fn save_document(&mut self, name: &str, doc: &Value) -> Res<()>;
+
+    /// Load the events in the log that come after the given sequence number,
+    /// in order
+    fn load_events(&mut self, after: u64) -> Res<Vec<Event>>;
+
+    /// Add events to the end of the log
+    fn append_events(&mut self, events: &[Event]) -> Res<()>;
+}
+
+/// The on-disk storage backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    Json,
+    Sqlite
+}
+
+impl StorageKind {
+
+    /// Find which backend the data in dir is stored with.
+    /// A directory without a database uses JSON
+    pub fn detect(dir: &Path) -> StorageKind {
+        if dir.join(SQLITE_FILE_NAME).exists() {
+            StorageKind::Sqlite
+        } else {
+            StorageKind::Json
+        }
+    }
+
+    /// Open the backend in dir
+    pub fn open(self, dir: &Path) -> Res<Box<dyn Storage>> {
+        match self {
+            StorageKind::Json => Ok(Box::new(JsonStorage::open(dir)?)),
+            StorageKind::Sqlite => Self::open_sqlite(dir)
+        }
+    }
+
+    /// The files this backend keeps in dir
+    pub fn file_paths(self, dir: &Path) -> Vec<PathBuf> {
+        match self {
+            StorageKind::Json => {
+                let mut paths = vec![
+                    dir.join(file::FILE_NAME),
+                    dir.join(json::ARCHIVE_FILE_NAME),
+                    dir.join(json::EVENTS_FILE_NAME)
+                ];
+                paths.extend(DOCUMENTS.iter().map(|name| dir.join(json::document_file_name(name))));
+
+                paths
+            },
+            StorageKind::Sqlite => vec![dir.join(SQLITE_FILE_NAME)]
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StorageKind::Json => "json",
+            StorageKind::Sqlite => "sqlite"
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    fn open_sqlite(dir: &Path) -> Res<Box<dyn Storage>> {
+        Ok(Box::new(SqliteStorage::open(dir)?))
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    fn open_sqlite(_dir: &Path) -> Res<Box<dyn Storage>> {
+        Err(ResErr::from(
+            "This data is stored in SQLite, but track was built without the sqlite feature"
+        ))
+    }
+}
+
+impl FromStr for StorageKind {
+    type Err = ResErr;
+
+    fn from_str(s: &str) -> Res<StorageKind> {
+        match s {
+            "json" => Ok(StorageKind::Json),
+            "sqlite" => Ok(StorageKind::Sqlite),
+            _ => Err(ResErr::from(format!("Unknown storage: {} (expected json or sqlite)", s)))
+        }
+    }
+}
+
+/// Open the storage the data in dir is kept in
+pub fn open(dir: &Path) -> Res<Box<dyn Storage>> {
+    StorageKind::detect(dir).open(dir)
+}
+
+/// Convert the data in dir to another backend.
+/// The old file is kept, renamed with a .migrated suffix,
+/// so that only one backend is ever detected.
+pub fn convert(dir: &Path, to: StorageKind) -> Res<StorageKind> {
+    let from = StorageKind::detect(dir);
+    if from == to {
+        return Err(ResErr::from(format!("Data is already stored as {}", to.name())));
+    }
+
+    {
+        let mut source = from.open(dir)?;
+        let manager = source.load()?
+            .ok_or_else(|| ResErr::from("There is no data to convert"))?;
+        let archive = source.load_archive()?;
+
+        let mut target = to.open(dir)?;
+        target.append_events(&source.load_events(0)?)?;
+        target.save(&manager)?;
+        target.save_archive(&archive)?;
+
+        for name in DOCUMENTS {
+            if let Some(doc) = source.load_document(name)? {
+                target.save_document(name, &doc)?;
+            }
+        }
+    }
+
+    for old_path in from.file_paths(dir).into_iter().filter(|p| p.exists()) {
+        let mut migrated = old_path.clone().into_os_string();
+        migrated.push(".migrated");
+
+        fs::rename(&old_path, migrated)?;
+    }
+
+    Ok(from)
+}
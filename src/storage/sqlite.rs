@@ -0,0 +1,679 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rusqlite::{params_from_iter, Connection};
+use rusqlite::types::Value as SqlValue;
+use serde_json::{Map, Value};
+
+use crate::{Res, ResErr};
+use crate::archive::Archive;
+use crate::event::Event;
+use crate::manager::Manager;
+use crate::migrate;
+use crate::storage::Storage;
+
+pub const FILE_NAME: &str = super::SQLITE_FILE_NAME;
+
+/// How long to wait for another process to finish with the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS groups (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        next_task INTEGER NOT NULL,
+        current_task INTEGER,
+        extra TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tasks (
+        group_id INTEGER NOT NULL,
+        id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        is_complete INTEGER NOT NULL,
+        extra TEXT NOT NULL,
+        PRIMARY KEY (group_id, id)
+    );
+    CREATE TABLE IF NOT EXISTS sessions (
+        group_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        idx INTEGER NOT NULL,
+        start INTEGER NOT NULL,
+        end INTEGER,
+        note TEXT,
+        extra TEXT NOT NULL,
+        PRIMARY KEY (group_id, task_id, idx)
+    );
+    CREATE TABLE IF NOT EXISTS archive (
+        archive_id INTEGER PRIMARY KEY,
+        version INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS documents (
+        name TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        seq INTEGER PRIMARY KEY,
+        at INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+";
+
+/// How a column is stored, so it can be turned back into JSON
+#[derive(Clone, Copy)]
+enum Kind {
+    Int,
+    Bool,
+    Text
+}
+
+// Columns stored for each kind of row, besides the key and the extra column.
+// Any field not listed here is kept in the extra column as JSON,
+// so new fields on the model don't need a new table layout.
+const GROUP_COLUMNS: &[(&str, Kind)] = &[
+    ("name", Kind::Text), ("next_task", Kind::Int), ("current_task", Kind::Int)
+];
+const TASK_COLUMNS: &[(&str, Kind)] = &[
+    ("name", Kind::Text), ("is_complete", Kind::Bool)
+];
+const SESSION_COLUMNS: &[(&str, Kind)] = &[
+    ("start", Kind::Int), ("end", Kind::Int), ("note", Kind::Text)
+];
+
+/// Identifies a single row across all tables
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RowKey {
+    Meta(String),
+    Group(i64),
+    Task(i64, i64),
+    Session(i64, i64, i64)
+}
+
+type Rows = HashMap<RowKey, Vec<SqlValue>>;
+type Object = Map<String, Value>;
+
+/// Stores groups, tasks and sessions as rows in a SQLite database.
+///
+/// The rows from the last load or save are remembered, so a save only
+/// writes the rows that changed. A write transaction is held for as
+/// long as this storage is open, so concurrent track processes
+/// can't overwrite each other's changes.
+///
+/// Archived groups are kept whole, as one JSON row per group.
+pub struct SqliteStorage {
+    conn: Connection,
+    rows: Rows,
+    archive_rows: HashMap<i64, String>
+}
+
+impl fmt::Debug for SqliteStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqliteStorage")
+            .field("path", &self.conn.path())
+            .field("rows", &self.rows.len())
+            .finish()
+    }
+}
+
+impl SqliteStorage {
+
+    /// Open the database in dir, creating it if needed
+    pub fn open(dir: &Path) -> Res<SqliteStorage> {
+        std::fs::create_dir_all(dir)?;
+
+        let conn = Connection::open(Self::file_path(dir))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(SCHEMA)?;
+
+        conn.execute_batch("BEGIN IMMEDIATE")
+            .map_err(|err| format!(
+                "Could not lock {}, it is in use by another track process: {}",
+                Self::file_path(dir).display(), err
+            ))?;
+
+        Ok(SqliteStorage { conn, rows: Rows::new(), archive_rows: HashMap::new() })
+    }
+
+    pub fn file_path(dir: &Path) -> PathBuf {
+        dir.join(FILE_NAME)
+    }
+
+    /// Commit what was written, but keep holding the write lock
+    /// until this storage is dropped
+    fn commit(&self) -> Res<()> {
+        self.conn.execute_batch("COMMIT; BEGIN IMMEDIATE")?;
+        Ok(())
+    }
+
+    fn read_rows(&self) -> Res<Rows> {
+        let mut rows = Rows::new();
+
+        let mut stmt = self.conn.prepare("SELECT key, value FROM meta")?;
+        let mut query = stmt.query([])?;
+        while let Some(row) = query.next()? {
+            rows.insert(RowKey::Meta(row.get(0)?), vec![row.get(1)?]);
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, next_task, current_task, extra FROM groups"
+        )?;
+        let mut query = stmt.query([])?;
+        while let Some(row) = query.next()? {
+            rows.insert(
+                RowKey::Group(row.get(0)?),
+                vec![row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?]
+            );
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT group_id, id, name, is_complete, extra FROM tasks"
+        )?;
+        let mut query = stmt.query([])?;
+        while let Some(row) = query.next()? {
+            rows.insert(
+                RowKey::Task(row.get(0)?, row.get(1)?),
+                vec![row.get(2)?, row.get(3)?, row.get(4)?]
+            );
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT group_id, task_id, idx, start, end, note, extra FROM sessions"
+        )?;
+        let mut query = stmt.query([])?;
+        while let Some(row) = query.next()? {
+            rows.insert(
+                RowKey::Session(row.get(0)?, row.get(1)?, row.get(2)?),
+                vec![row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?]
+            );
+        }
+
+        Ok(rows)
+    }
+
+    fn upsert(&self, key: &RowKey, values: &[SqlValue]) -> Res<()> {
+        let (sql, mut params) = match key {
+            RowKey::Meta(k) => (
+                "INSERT OR REPLACE INTO meta (key, value) VALUES (?, ?)",
+                vec![SqlValue::Text(k.clone())]
+            ),
+            RowKey::Group(id) => (
+                "INSERT OR REPLACE INTO groups
+                    (id, name, next_task, current_task, extra) VALUES (?, ?, ?, ?, ?)",
+                vec![SqlValue::Integer(*id)]
+            ),
+            RowKey::Task(group, id) => (
+                "INSERT OR REPLACE INTO tasks
+                    (group_id, id, name, is_complete, extra) VALUES (?, ?, ?, ?, ?)",
+                vec![SqlValue::Integer(*group), SqlValue::Integer(*id)]
+            ),
+            RowKey::Session(group, task, idx) => (
+                "INSERT OR REPLACE INTO sessions
+                    (group_id, task_id, idx, start, end, note, extra) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                vec![SqlValue::Integer(*group), SqlValue::Integer(*task), SqlValue::Integer(*idx)]
+            )
+        };
+
+        params.extend_from_slice(values);
+        self.conn.execute(sql, params_from_iter(params))?;
+
+        Ok(())
+    }
+
+    fn delete(&self, key: &RowKey) -> Res<()> {
+        match key {
+            RowKey::Meta(k) => self.conn.execute("DELETE FROM meta WHERE key = ?", [k])?,
+            RowKey::Group(id) => self.conn.execute("DELETE FROM groups WHERE id = ?", [id])?,
+            RowKey::Task(group, id) => self.conn.execute(
+                "DELETE FROM tasks WHERE group_id = ? AND id = ?", [group, id]
+            )?,
+            RowKey::Session(group, task, idx) => self.conn.execute(
+                "DELETE FROM sessions WHERE group_id = ? AND task_id = ? AND idx = ?",
+                [group, task, idx]
+            )?
+        };
+
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+
+    fn load(&mut self) -> Res<Option<Manager>> {
+        let rows = self.read_rows()?;
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let mut doc = to_doc(&rows)?;
+        migrate::migrate(&mut doc)?;
+
+        let manager = serde_json::from_value(doc)?;
+        self.rows = rows;
+
+        Ok(Some(manager))
+    }
+
+    fn save(&mut self, manager: &Manager) -> Res<()> {
+        let rows = to_rows(&serde_json::to_value(manager)?)?;
+
+        for (key, values) in &rows {
+            if self.rows.get(key) != Some(values) {
+                self.upsert(key, values)?;
+            }
+        }
+
+        for key in self.rows.keys() {
+            if !rows.contains_key(key) {
+                self.delete(key)?;
+            }
+        }
+
+        self.commit()?;
+        self.rows = rows;
+
+        Ok(())
+    }
+
+    fn load_archive(&mut self) -> Res<Archive> {
+        let mut groups: Vec<Value> = Vec::new();
+        let mut archive_rows = HashMap::new();
+
+        let mut stmt = self.conn.prepare(
+            "SELECT archive_id, version, data FROM archive ORDER BY archive_id"
+        )?;
+        let mut query = stmt.query([])?;
+        while let Some(row) = query.next()? {
+            let archive_id: i64 = row.get(0)?;
+            let version: i64 = row.get(1)?;
+            let data: String = row.get(2)?;
+
+            // Each row is migrated on its own, since rows are only
+            // rewritten when that group changes
+            let mut doc = serde_json::json!({
+                "version": version,
+                "groups": [serde_json::from_str::<Value>(&data)?]
+            });
+            migrate::migrate(&mut doc)?;
+
+            if let Some(Value::Array(mut migrated)) = doc.get_mut("groups").map(Value::take) {
+                groups.append(&mut migrated);
+            }
+
+            archive_rows.insert(archive_id, data);
+        }
+
+        let archive = serde_json::from_value(serde_json::json!({
+            "version": migrate::SCHEMA_VERSION,
+            "groups": groups
+        }))?;
+        self.archive_rows = archive_rows;
+
+        Ok(archive)
+    }
+
+    fn save_archive(&mut self, archive: &Archive) -> Res<()> {
+        let doc = serde_json::to_value(archive)?;
+        let mut archive_rows = HashMap::new();
+
+        let obj = doc.as_object()
+            .ok_or_else(|| ResErr::from("Archive is not a JSON object"))?;
+
+        for group in children(obj, "groups") {
+            let archive_id = int_field(group, "archive_id")?;
+            archive_rows.insert(archive_id, Value::Object(group.clone()).to_string());
+        }
+
+        for (archive_id, data) in &archive_rows {
+            if self.archive_rows.get(archive_id) != Some(data) {
+                self.conn.execute(
+                    "INSERT OR REPLACE INTO archive (archive_id, version, data) VALUES (?, ?, ?)",
+                    rusqlite::params![archive_id, migrate::SCHEMA_VERSION as i64, data]
+                )?;
+            }
+        }
+
+        for archive_id in self.archive_rows.keys() {
+            if !archive_rows.contains_key(archive_id) {
+                self.conn.execute("DELETE FROM archive WHERE archive_id = ?", [archive_id])?;
+            }
+        }
+
+        self.commit()?;
+        self.archive_rows = archive_rows;
+
+        Ok(())
+    }
+
+    fn load_document(&mut self, name: &str) -> Res<Option<Value>> {
+        let mut stmt = self.conn.prepare("SELECT data FROM documents WHERE name = ?")?;
+        let mut query = stmt.query([name])?;
+
+        match query.next()? {
+            Some(row) => Ok(Some(serde_json::from_str(&row.get::<_, String>(0)?)?)),
+            None => Ok(None)
+        }
+    }
+
+    fn save_document(&mut self, name: &str, doc: &Value) -> Res<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO documents (name, data) VALUES (?, ?)",
+            [name, &doc.to_string()]
+        )?;
+
+        self.commit()
+    }
+
+    fn load_events(&mut self, after: u64) -> Res<Vec<Event>> {
+        let mut stmt = self.conn.prepare("SELECT data FROM events WHERE seq > ? ORDER BY seq")?;
+        let mut query = stmt.query([after as i64])?;
+
+        let mut events = Vec::new();
+        while let Some(row) = query.next()? {
+            events.push(serde_json::from_str(&row.get::<_, String>(0)?)?);
+        }
+
+        Ok(events)
+    }
+
+    fn append_events(&mut self, events: &[Event]) -> Res<()> {
+        for event in events {
+            self.conn.execute(
+                "INSERT INTO events (seq, at, data) VALUES (?, ?, ?)",
+                rusqlite::params![event.seq() as i64, event.at(), serde_json::to_string(event)?]
+            )?;
+        }
+
+        self.commit()
+    }
+}
+
+// --- ROW CONVERSION ---
+
+/// Flatten a manager document into table rows
+fn to_rows(doc: &Value) -> Res<Rows> {
+    let mut rows = Rows::new();
+
+    let obj = doc.as_object()
+        .ok_or_else(|| ResErr::from("Manager is not a JSON object"))?;
+
+    for (key, value) in obj {
+        if key != "groups" {
+            rows.insert(RowKey::Meta(key.clone()), vec![SqlValue::Text(value.to_string())]);
+        }
+    }
+
+    for group in children(obj, "groups") {
+        let group_id = int_field(group, "id")?;
+        rows.insert(RowKey::Group(group_id), split(group, GROUP_COLUMNS, &["id", "tasks"])?);
+
+        for task in children(group, "tasks") {
+            let task_id = int_field(task, "id")?;
+            rows.insert(
+                RowKey::Task(group_id, task_id),
+                split(task, TASK_COLUMNS, &["id", "sessions"])?
+            );
+
+            for (idx, session) in children(task, "sessions").enumerate() {
+                rows.insert(
+                    RowKey::Session(group_id, task_id, idx as i64),
+                    split(session, SESSION_COLUMNS, &[])?
+                );
+            }
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Rebuild a manager document from table rows
+fn to_doc(rows: &Rows) -> Res<Value> {
+    let mut doc = Object::new();
+    let mut groups: Vec<(i64, Object)> = Vec::new();
+    let mut tasks: Vec<((i64, i64), Object)> = Vec::new();
+    let mut sessions: Vec<((i64, i64, i64), Object)> = Vec::new();
+
+    for (key, values) in rows {
+        match key {
+            RowKey::Meta(k) => {
+                let text = match values.first() {
+                    Some(SqlValue::Text(text)) => text,
+                    _ => return Err(ResErr::from(format!("Invalid meta value for {}", k)))
+                };
+                doc.insert(k.clone(), serde_json::from_str(text)?);
+            },
+            RowKey::Group(id) => {
+                let mut group = join(values, GROUP_COLUMNS)?;
+                group.insert(String::from("id"), Value::from(*id));
+                groups.push((*id, group));
+            },
+            RowKey::Task(group_id, id) => {
+                let mut task = join(values, TASK_COLUMNS)?;
+                task.insert(String::from("id"), Value::from(*id));
+                tasks.push(((*group_id, *id), task));
+            },
+            RowKey::Session(group_id, task_id, idx) => {
+                sessions.push(((*group_id, *task_id, *idx), join(values, SESSION_COLUMNS)?));
+            }
+        }
+    }
+
+    groups.sort_by_key(|(k, _)| *k);
+    tasks.sort_by_key(|(k, _)| *k);
+    sessions.sort_by_key(|(k, _)| *k);
+
+    // Attach children to their parents, from the bottom up
+    for ((group_id, task_id, _), session) in sessions {
+        let task = tasks.iter_mut()
+            .find(|(k, _)| *k == (group_id, task_id))
+            .ok_or_else(|| ResErr::from("Session belongs to a missing task"))?;
+
+        push_child(&mut task.1, "sessions", session);
+    }
+
+    for ((group_id, _), mut task) in tasks {
+        task.entry("sessions").or_insert_with(|| Value::Array(Vec::new()));
+
+        let group = groups.iter_mut()
+            .find(|(k, _)| *k == group_id)
+            .ok_or_else(|| ResErr::from("Task belongs to a missing group"))?;
+
+        push_child(&mut group.1, "tasks", task);
+    }
+
+    let groups = groups.into_iter()
+        .map(|(_, mut group)| {
+            group.entry("tasks").or_insert_with(|| Value::Array(Vec::new()));
+            Value::Object(group)
+        })
+        .collect();
+
+    doc.insert(String::from("groups"), Value::Array(groups));
+
+    Ok(Value::Object(doc))
+}
+
+/// Iterate the objects in an array field
+fn children<'a>(obj: &'a Map<String, Value>, key: &str) -> impl Iterator<Item = &'a Map<String, Value>> {
+    obj.get(key)
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_object())
+}
+
+fn push_child(obj: &mut Map<String, Value>, key: &str, child: Map<String, Value>) {
+    if let Some(Value::Array(arr)) = obj.get_mut(key) {
+        arr.push(Value::Object(child));
+    } else {
+        obj.insert(String::from(key), Value::Array(vec![Value::Object(child)]));
+    }
+}
+
+fn int_field(obj: &Map<String, Value>, key: &str) -> Res<i64> {
+    obj.get(key)
+        .and_then(|v| v.as_i64())
+        .ok_or_else(|| ResErr::from(format!("Missing integer field: {}", key)))
+}
+
+/// Split an object into its column values, followed by
+/// the extra column holding every other field
+fn split(obj: &Map<String, Value>, columns: &[(&str, Kind)], skip: &[&str]) -> Res<Vec<SqlValue>> {
+    let mut values = Vec::new();
+
+    for (name, kind) in columns {
+        let value = match (obj.get(*name), kind) {
+            (None, _) | (Some(Value::Null), _) => SqlValue::Null,
+            (Some(Value::Bool(b)), Kind::Bool) => SqlValue::Integer(*b as i64),
+            (Some(Value::String(s)), Kind::Text) => SqlValue::Text(s.clone()),
+            (Some(v), Kind::Int) if v.is_i64() => SqlValue::Integer(v.as_i64().unwrap_or(0)),
+            (Some(v), _) => return Err(ResErr::from(format!("Unexpected value for {}: {}", name, v)))
+        };
+
+        values.push(value);
+    }
+
+    let extra: Map<String, Value> = obj.iter()
+        .filter(|(k, _)| !skip.contains(&k.as_str()) && !columns.iter().any(|(c, _)| c == k))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+
+    values.push(SqlValue::Text(Value::Object(extra).to_string()));
+
+    Ok(values)
+}
+
+/// Reverse of split
+fn join(values: &[SqlValue], columns: &[(&str, Kind)]) -> Res<Map<String, Value>> {
+    let mut obj = match values.get(columns.len()) {
+        Some(SqlValue::Text(extra)) => match serde_json::from_str(extra)? {
+            Value::Object(obj) => obj,
+            _ => Map::new()
+        },
+        _ => Map::new()
+    };
+
+    for ((name, kind), value) in columns.iter().zip(values) {
+        let json = match (value, kind) {
+            (SqlValue::Null, _) => Value::Null,
+            (SqlValue::Integer(i), Kind::Bool) => Value::Bool(*i != 0),
+            (SqlValue::Integer(i), _) => Value::from(*i),
+            (SqlValue::Text(s), _) => Value::String(s.clone()),
+            _ => return Err(ResErr::from(format!("Unexpected column value for {}", name)))
+        };
+
+        obj.insert(String::from(*name), json);
+    }
+
+    Ok(obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn doc() -> Value {
+        json!({
+            "version": migrate::SCHEMA_VERSION,
+            "next_group": 3,
+            "current_group": null,
+            "groups": [
+                {
+                    "id": 1,
+                    "uid": 1,
+                    "name": "10-15-2020",
+                    "next_task": 3,
+                    "current_task": 2,
+                    "tasks": [
+                        {
+                            "id": 1,
+                            "uid": 2,
+                            "name": "a",
+                            "is_complete": true,
+                            "sessions": [
+                                { "start": 100, "end": 200, "note": "first" },
+                                { "start": 300, "end": 400, "note": null, "kind": "logged" }
+                            ],
+                            "pauses": [{ "start": 200, "end": 250 }]
+                        },
+                        {
+                            "id": 2,
+                            "uid": 3,
+                            "name": "b",
+                            "is_complete": false,
+                            "sessions": [
+                                { "start": 500, "end": null, "note": null, "timebox": { "seconds": 1500, "pomodoro": true } }
+                            ]
+                        }
+                    ]
+                },
+                { "id": 2, "uid": 4, "name": "empty", "next_task": 1, "current_task": null, "tasks": [] }
+            ]
+        })
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        let doc = doc();
+        let rows = to_rows(&doc).unwrap();
+
+        assert_eq!(rows.keys().filter(|k| matches!(k, RowKey::Group(_))).count(), 2);
+        assert_eq!(rows.keys().filter(|k| matches!(k, RowKey::Task(..))).count(), 2);
+        assert_eq!(rows.keys().filter(|k| matches!(k, RowKey::Session(..))).count(), 3);
+
+        assert_eq!(to_doc(&rows).unwrap(), doc);
+    }
+
+    #[test]
+    fn unknown_fields_are_kept_as_extra() {
+        let rows = to_rows(&doc()).unwrap();
+
+        let task = &rows[&RowKey::Task(1, 1)];
+        assert_eq!(task[0], SqlValue::Text(String::from("a")));
+        assert_eq!(task[1], SqlValue::Integer(1));
+
+        let extra: Value = match &task[TASK_COLUMNS.len()] {
+            SqlValue::Text(extra) => serde_json::from_str(extra).unwrap(),
+            other => panic!("Expected the extra column, found {:?}", other)
+        };
+        assert_eq!(extra, json!({ "uid": 2, "pauses": [{ "start": 200, "end": 250 }] }));
+    }
+
+    #[test]
+    fn manager_round_trip() {
+        let mut manager = Manager::with_storage(Box::new(MemoryStorage::new())).unwrap();
+        manager.add_task(String::from("a")).unwrap();
+        let task = manager.find_task("a").unwrap();
+        manager.log_time(task, 60 * 60, None, Some(String::from("note"))).unwrap();
+        manager.start_task(task, None, None, None, false).unwrap();
+
+        let doc = serde_json::to_value(&manager).unwrap();
+        let rebuilt = to_doc(&to_rows(&doc).unwrap()).unwrap();
+
+        let loaded: Manager = serde_json::from_value(rebuilt).unwrap();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), doc);
+    }
+
+    #[test]
+    fn to_rows_rejects_invalid_documents() {
+        assert!(to_rows(&json!([])).is_err());
+        assert!(to_rows(&json!({ "groups": [{ "name": "no id" }] })).is_err());
+        assert!(to_rows(&json!({ "groups": [{ "id": 1, "name": 5 }] })).is_err());
+    }
+
+    #[test]
+    fn to_doc_rejects_orphans() {
+        let mut rows = to_rows(&doc()).unwrap();
+        rows.remove(&RowKey::Group(1));
+
+        assert!(to_doc(&rows).is_err());
+    }
+}
@@ -1,21 +1,57 @@
-use chrono::{offset::TimeZone, DateTime, Utc, Local, NaiveDateTime, Duration, Date};
+use chrono::{offset::TimeZone, DateTime, Timelike, Utc, Local, NaiveDate, NaiveDateTime, NaiveTime, Duration, Date};
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{Res, ResErr};
+
+/// The hour of the local day that days start at.
+/// Before it, times still belong to the day before
+static DAY_START_HOUR: AtomicU32 = AtomicU32::new(0);
+
+/// Set the hour days start at, e.g. 4 so work until 4am counts as the day before
+pub fn set_day_start(hour: u32) {
+    DAY_START_HOUR.store(hour, Ordering::Relaxed);
+}
+
+pub fn day_start() -> u32 {
+    DAY_START_HOUR.load(Ordering::Relaxed)
+}
 
 pub fn today() -> Date<Utc> {
     Utc::now().date()
 }
 
+/// The local day it is now, which only rolls over at the day start hour
 pub fn today_local() -> Date<Local> {
-    Utc::now().with_timezone(&Local).date()
+    day_of(timestamp())
 }
 
 pub fn tomorrow_local() -> Date<Local> {
     today_local() + Duration::days(1)
 }
 
+/// Parse a day, as "today", "yesterday", "tomorrow" or YYYY-MM-DD.
+/// Days follow the day start hour, like today_local
+pub fn parse_day(value: &str) -> Option<NaiveDate> {
+    let today = today_local().naive_local();
+
+    match value.trim().to_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        "tomorrow" => today.succ_opt(),
+        other => NaiveDate::parse_from_str(other, "%Y-%m-%d").ok()
+    }
+}
+
 pub fn timestamp() -> i64 {
     Utc::now().timestamp()
 }
 
+/// The timestamp, if it is within the range of dates that can be shown
+pub fn checked_timestamp(stamp: i64) -> Option<i64> {
+    NaiveDateTime::from_timestamp_opt(stamp, 0).map(|_| stamp)
+}
+
 pub fn to_datetime(stamp: i64) -> DateTime<Utc> {
     Utc.from_utc_datetime(&NaiveDateTime::from_timestamp(stamp, 0))
 }
@@ -24,12 +60,281 @@ pub fn to_local_datetime(stamp: i64) -> DateTime<Local> {
     to_datetime(stamp).with_timezone(&Local)
 }
 
+/// The local day the timestamp belongs to, taking the day start hour into account
+pub fn day_of(stamp: i64) -> Date<Local> {
+    (to_local_datetime(stamp) - Duration::hours(day_start() as i64)).date()
+}
+
+/// The start of the next local day after the timestamp
+pub fn next_day_start(stamp: i64) -> i64 {
+    let next = day_of(stamp).naive_local().succ().and_hms(day_start(), 0, 0);
+
+    Local.from_local_datetime(&next)
+        .earliest()
+        .map(|t| t.timestamp())
+        // The start can be skipped by a DST change, then the day starts an hour later
+        .unwrap_or_else(|| stamp + (next - to_local_datetime(stamp).naive_local()).num_seconds() + 3600)
+}
+
+/// Format a timestamp as a local date and time, the way parse_at accepts it
+pub fn format_local(stamp: i64) -> String {
+    to_local_datetime(stamp).format("%Y-%m-%d %H:%M").to_string()
+}
+
 pub fn duration_str(stamp: i64) -> String {
-    let duration = Duration::seconds(stamp);
+    let abs = stamp.unsigned_abs();
 
-    format!("{}h, {}m, {}s",
-        duration.num_hours(),
-        duration.num_minutes() % 60,
-        duration.num_seconds() % 60
+    format!("{}{}h, {}m, {}s",
+        if stamp < 0 { "-" } else { "" },
+        abs / 3600,
+        abs / 60 % 60,
+        abs % 60
     )
-}
\ No newline at end of file
+}
+
+/// Format a length of time the way parse_signed_duration accepts it,
+/// e.g. "1h30m" or "-15m"
+pub fn duration_arg(seconds: i64) -> String {
+    let abs = seconds.unsigned_abs();
+    let mut arg = String::from(if seconds < 0 { "-" } else { "" });
+
+    for (amount, unit) in &[(abs / 3600, "h"), (abs / 60 % 60, "m"), (abs % 60, "s")] {
+        if *amount > 0 {
+            arg += &format!("{}{}", amount, unit);
+        }
+    }
+
+    if abs == 0 { String::from("0s") } else { arg }
+}
+
+/// Parse a local date and time, "2020-10-15 14:00", or a local date,
+/// "2020-10-15", which is taken as the end of that day
+pub fn parse_local(value: &str) -> Res<i64> {
+    let value = value.trim();
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .map(|d| d.succ().and_hms(day_start(), 0, 0) - Duration::seconds(1)))
+        .ok_or_else(|| ResErr::from(format!("Could not parse time: {}", value)))?;
+
+    Local.from_local_datetime(&naive)
+        .earliest()
+        .map(|t| t.timestamp())
+        .ok_or_else(|| ResErr::from(format!("No such local time: {}", value)))
+}
+
+/// The longest length of time that can be parsed. Far longer than
+/// anything worth tracking, and far from overflowing the timestamps
+/// it is added to
+pub const MAX_DURATION: i64 = 10_000 * 60 * 60;
+
+/// Parse a length of time such as "1h30m", "20m" or "45s" into seconds
+pub fn parse_duration(value: &str) -> Res<i64> {
+    let invalid = || ResErr::from(format!("Could not parse duration: {}", value));
+
+    let mut seconds: i64 = 0;
+    let mut digits = String::new();
+    let mut found = false;
+
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid())
+        };
+
+        let amount = digits.parse::<i64>().map_err(|_| invalid())?;
+        seconds = amount.checked_mul(unit)
+            .and_then(|amount| seconds.checked_add(amount))
+            .ok_or_else(invalid)?;
+        digits.clear();
+        found = true;
+    }
+
+    if !digits.is_empty() || !found {
+        return Err(invalid());
+    }
+
+    if seconds > MAX_DURATION {
+        return Err(ResErr::from(format!(
+            "{} is too long, durations can be at most {}", value.trim(), duration_arg(MAX_DURATION)
+        )));
+    }
+
+    Ok(seconds)
+}
+
+/// Parse a length of time that may be negative, such as "-15m" or "+1h"
+pub fn parse_signed_duration(value: &str) -> Res<i64> {
+    let value = value.trim();
+
+    match value.strip_prefix('-') {
+        Some(rest) => Ok(-parse_duration(rest)?),
+        _ => parse_duration(value.strip_prefix('+').unwrap_or(value))
+    }
+}
+
+/// Parse the time something happened, relative to now.
+///
+/// Accepts a local time today ("09:15"), a local date and time
+/// ("2020-10-15 14:00"), a time before now ("-20m", "-1h30m")
+/// or the same in words ("30 minutes ago", "2 hours ago").
+pub fn parse_at(value: &str, now: i64) -> Res<i64> {
+    let value = value.trim();
+    let invalid = || ResErr::from(format!(
+        "Could not parse time: {}. Use e.g. 09:15, \"2020-10-15 14:00\", -20m or \"30 minutes ago\"",
+        value
+    ));
+
+    if let Some(ago) = value.strip_prefix('-') {
+        return parse_duration(ago).ok()
+            .and_then(|ago| now.checked_sub(ago))
+            .and_then(checked_timestamp)
+            .ok_or_else(invalid);
+    }
+
+    if let Some(ago) = value.strip_suffix("ago") {
+        let words: Vec<&str> = ago.split_whitespace().collect();
+        if words.len() != 2 {
+            return Err(invalid());
+        }
+
+        let amount = words[0].parse::<i64>().map_err(|_| invalid())?;
+        let unit = match words[1].trim_end_matches('s') {
+            "hour" | "hr" | "h" => 60 * 60,
+            "minute" | "min" | "m" => 60,
+            "second" | "sec" => 1,
+            _ => return Err(invalid())
+        };
+
+        return amount.checked_mul(unit)
+            .and_then(|ago| now.checked_sub(ago))
+            .and_then(checked_timestamp)
+            .ok_or_else(invalid);
+    }
+
+    let naive = match NaiveTime::parse_from_str(value, "%H:%M") {
+        // A time before the day start is in the early hours of the next date
+        Ok(t) if t.hour() < day_start() => day_of(now).naive_local().succ().and_time(t),
+        Ok(t) => day_of(now).naive_local().and_time(t),
+        _ => NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+            .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+            .map_err(|_| invalid())?
+    };
+
+    Local.from_local_datetime(&naive)
+        .earliest()
+        .map(|t| t.timestamp())
+        .ok_or_else(|| ResErr::from(format!("No such local time: {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_adds_up_units() {
+        assert_eq!(parse_duration("1h30m").unwrap(), 90 * 60);
+        assert_eq!(parse_duration("20m").unwrap(), 20 * 60);
+        assert_eq!(parse_duration(" 45s ").unwrap(), 45);
+        assert_eq!(parse_duration("1h1m1s").unwrap(), 3661);
+    }
+
+    #[test]
+    fn parse_duration_rejects_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("5d").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflow() {
+        assert!(parse_duration("9999999999999999h").is_err());
+        assert!(parse_duration("9223372036854775807s1s").is_err());
+        assert!(parse_duration("2562047788015216h").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+    }
+
+    #[test]
+    fn parse_duration_is_capped() {
+        assert_eq!(parse_duration("10000h").unwrap(), MAX_DURATION);
+        assert!(parse_duration("10000h1s").is_err());
+        assert!(parse_duration("3000000000000h").is_err());
+        assert!(parse_signed_duration("-10001h").is_err());
+    }
+
+    #[test]
+    fn duration_str_formats_any_length() {
+        assert_eq!(duration_str(90 * 60 + 5), "1h, 30m, 5s");
+        assert_eq!(duration_str(-15 * 60), "-0h, 15m, 0s");
+        assert_eq!(duration_str(i64::MIN), "-2562047788015215h, 30m, 8s");
+    }
+
+    #[test]
+    fn parse_signed_duration_keeps_sign() {
+        assert_eq!(parse_signed_duration("-15m").unwrap(), -15 * 60);
+        assert_eq!(parse_signed_duration("+1h").unwrap(), 60 * 60);
+        assert_eq!(parse_signed_duration("30s").unwrap(), 30);
+    }
+
+    #[test]
+    fn parse_at_relative_to_now() {
+        let now = 1_600_000_000;
+
+        assert_eq!(parse_at("-20m", now).unwrap(), now - 20 * 60);
+        assert_eq!(parse_at("-1h30m", now).unwrap(), now - 90 * 60);
+        assert_eq!(parse_at("30 minutes ago", now).unwrap(), now - 30 * 60);
+        assert_eq!(parse_at("2 hours ago", now).unwrap(), now - 2 * 60 * 60);
+        assert_eq!(parse_at("1 hour ago", now).unwrap(), now - 60 * 60);
+        assert_eq!(parse_at("10 secs ago", now).unwrap(), now - 10);
+    }
+
+    #[test]
+    fn parse_at_local_date_and_time() {
+        let expected = Local.from_local_datetime(
+            &NaiveDate::from_ymd(2020, 10, 15).and_hms(14, 0, 0)
+        ).earliest().unwrap().timestamp();
+
+        assert_eq!(parse_at("2020-10-15 14:00", timestamp()).unwrap(), expected);
+        assert_eq!(parse_at("2020-10-15 14:00:00", timestamp()).unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_at_time_today() {
+        let now = timestamp();
+        let at = parse_at("09:15", now).unwrap();
+
+        let local = to_local_datetime(at);
+        assert_eq!((local.hour(), local.minute()), (9, 15));
+        assert_eq!(local.date(), to_local_datetime(now).date());
+    }
+
+    #[test]
+    fn parse_at_rejects_invalid() {
+        let now = timestamp();
+
+        assert!(parse_at("yesterday-ish", now).is_err());
+        assert!(parse_at("3 weeks ago", now).is_err());
+        assert!(parse_at("minutes ago", now).is_err());
+        assert!(parse_at("25:00", now).is_err());
+    }
+
+    #[test]
+    fn parse_at_rejects_overflow() {
+        let now = timestamp();
+
+        assert!(parse_at("-9999999999999999h", now).is_err());
+        assert!(parse_at("-2562047788015h", now).is_err());
+        assert!(parse_at("99999999999999 hours ago", now).is_err());
+    }
+}